@@ -0,0 +1,72 @@
+//! Throughput and latency of the solver over the bundled puzzle files.
+//! Run with `cargo bench`; every file is reported separately, including its slowest puzzle.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::time::{Duration, Instant};
+
+use sudoku_solver::sudokutwo::Sudoku;
+use sudoku_solver::sudokutwo::sudoku_api::SudokuApi;
+
+const FILES: [&str; 2] = ["resources/sudoku-easy-50.txt", "resources/top-95.txt"];
+
+/// Every puzzle is solved this many times, and its fastest time is kept to filter out noise
+const RUNS: usize = 5;
+
+struct Report {
+    times: Vec<Duration>,
+    solved: usize,
+}
+
+fn solve_once_loop(s: &mut Sudoku) -> bool {
+    while s.solve_once() > 0 {}
+    s.is_solved()
+}
+
+fn full_solver(s: &mut Sudoku) -> bool {
+    s.attempt_solve()
+}
+
+fn measure(puzzles: &[String], solve: fn(&mut Sudoku) -> bool) -> Report {
+    let mut times = vec!();
+    let mut solved = 0;
+    for line in puzzles {
+        let mut best = Duration::MAX;
+        let mut is_solved = false;
+        for _ in 0..RUNS {
+            let mut s = Sudoku::new(line.clone()).expect("bundled puzzles are valid");
+            let start = Instant::now();
+            is_solved = solve(&mut s);
+            best = best.min(start.elapsed());
+        }
+        times.push(best);
+        if is_solved {
+            solved += 1;
+        }
+    }
+    Report { times, solved }
+}
+
+fn print_report(file: &str, name: &str, report: &Report) {
+    let total: Duration = report.times.iter().sum();
+    let mut sorted = report.times.clone();
+    sorted.sort();
+    let (worst_index, worst) = report.times.iter().enumerate()
+        .max_by_key(|(_, t)| **t)
+        .expect("puzzle file is empty");
+    println!("{} / {}: solved {}/{}, {:.0} puzzles/s, median {:?}, p95 {:?}, worst {:?} (line {})",
+             file, name, report.solved, report.times.len(),
+             report.times.len() as f64 / total.as_secs_f64(),
+             sorted[sorted.len() / 2],
+             sorted[(sorted.len() * 95 / 100).min(sorted.len() - 1)],
+             worst, worst_index + 1);
+}
+
+fn main() -> io::Result<()> {
+    for file in FILES.iter() {
+        let puzzles = BufReader::new(File::open(file)?).lines().collect::<io::Result<Vec<_>>>()?;
+        print_report(file, "solve_once loop", &measure(&puzzles, solve_once_loop));
+        print_report(file, "full solver", &measure(&puzzles, full_solver));
+    }
+    Ok(())
+}
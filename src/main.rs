@@ -1,23 +1,92 @@
+use std::env;
 use std::fs::File;
 use std::io::{self, BufReader, prelude::*};
 
-use crate::sudokutwo::sudoku_api::SudokuApi;
-
-mod sudokutwo;
+use sudoku_solver::sudokutwo::{batch, play, verify};
+use sudoku_solver::sudokutwo::Sudoku;
+use sudoku_solver::sudokutwo::sudoku_api::SudokuApi;
 
+/// Usage:
+///   sudoku-solver                                  solve resources/sudoku-easy-1.txt once
+///   sudoku-solver batch [FILE] [--threads N]      solve every line of FILE (or stdin) on all cores
+///   sudoku-solver verify PUZZLES SOLUTIONS         check the solutions line by line against the puzzles
+///   sudoku-solver verify CSV                       the same for `puzzle,solution` lines
+///   sudoku-solver play [FILE]                      play the first puzzle of FILE (or resources/sudoku-easy-1.txt)
 fn main() -> io::Result<()> {
-    let file = File::open("resources/sudoku-easy-1.txt")?;
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("batch") => run_batch(&args[1..]),
+        Some("verify") => run_verify(&args[1..]),
+        Some("play") => run_play(args.get(1).map_or("resources/sudoku-easy-1.txt", String::as_str)),
+        _ => solve_once_file("resources/sudoku-easy-1.txt"),
+    }
+}
+
+fn solve_once_file(path: &str) -> io::Result<()> {
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
     for line in reader.lines() {
-        let mut s = match crate::sudokutwo::Sudoku::new(line?) {
+        let mut s = match Sudoku::new(line?) {
             Ok(s) => s,
-            Err(msg) => panic!(msg),
+            Err(msg) => panic!("{}", msg),
         };
         s.solve_once();
     }
     Ok(())
 }
 
+fn run_batch(args: &[String]) -> io::Result<()> {
+    let mut threads = batch::default_threads();
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threads" => threads = args.next()
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--threads needs a number"))?,
+            _ => path = Some(arg),
+        }
+    }
+    let stdout = io::stdout();
+    let output = io::BufWriter::new(stdout.lock());
+    let count = match path {
+        Some(path) => batch::solve_stream(BufReader::new(File::open(path)?), output, threads)?,
+        None => batch::solve_stream(io::stdin().lock(), output, threads)?,
+    };
+    eprintln!("Solved {} puzzles on {} threads", count, threads);
+    Ok(())
+}
+
+/// Print a line per puzzle, and exit with an error if any solution fails
+fn run_verify(args: &[String]) -> io::Result<()> {
+    let open = |path: &String| File::open(path).map(BufReader::new);
+    let report = match args {
+        [csv] => verify::verify_csv(open(csv)?)?,
+        [puzzles, solutions] => verify::verify_files(open(puzzles)?, open(solutions)?)?,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "verify needs a CSV file, or a puzzle and a solution file")),
+    };
+    let stdout = io::stdout();
+    let mut output = io::BufWriter::new(stdout.lock());
+    for verification in report.iter() {
+        writeln!(output, "{}", verification)?;
+    }
+    output.flush()?;
+    let passed = report.iter().filter(|v| v.passed()).count();
+    eprintln!("{} of {} solutions pass", passed, report.len());
+    if passed < report.len() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn run_play(path: &str) -> io::Result<()> {
+    let line = BufReader::new(File::open(path)?).lines().next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the file has no puzzle"))??;
+    let puzzle = Sudoku::new(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let stdin = io::stdin();
+    play::run(&puzzle, stdin.lock(), io::stdout())
+}
+
 //fn main_one() -> io::Result<()> {
 //    let file = File::open("resources/top-95.txt")?;
 //    let reader = BufReader::new(file);
@@ -1,35 +1,54 @@
-use std::borrow::BorrowMut;
 use std::fmt::{Display, Error, Formatter};
+use std::sync::Arc;
 
-use crate::sudokutwo::entry_num::{EntryNum, EntryNumThings};
-use crate::sudokutwo::sudoku_api::SudokuApi;
+use crate::sudokutwo::constraint::Constraint;
+use crate::sudokutwo::entry_num::{ALL, EntryNum, EntryNumThings};
+use crate::sudokutwo::layout::Layout;
+use crate::sudokutwo::origin::Origin;
+use crate::sudokutwo::technique::Elimination;
 
 pub mod sudoku_api;
-mod entry_num;
+pub mod anti_chess;
+pub mod batch;
+pub mod canonical;
+pub mod constraint;
+pub mod description;
+pub mod dots;
+pub mod entry_num;
+pub mod game;
+pub mod killer;
+pub mod layout;
+pub mod lines;
+pub mod minimal;
+pub mod origin;
+pub mod play;
+pub mod outside;
+pub mod samurai;
+pub mod solver_config;
+pub mod state;
+pub mod technique;
+pub mod trace;
+pub mod transform;
+pub mod validation;
+pub mod verify;
 mod sudoku_essentials;
 
-enum EntityType {
-    Box,
-    Col,
-    Row,
-}
-
 impl Sudoku {
-    fn get_sets(sets: Vec<EntryNum>, all_pots: EntryNum, data: &[EntryNum; 81]) -> EntryNum {
-//        sets.sort_by_key(|x|x.count_ones());
+    fn get_sets(sets: &[EntryNum], all_pots: EntryNum) -> EntryNum {
         let mut res = 0;
-        for set in &sets {
-            let mut cur = set.clone();
+        let mut remaining: [EntryNum; 9] = [0; 9];
+        let remaining = &mut remaining[..sets.len()];
+        for set in sets {
+            let mut cur = *set;
             // if cur subset of res
             if (cur ^ res) & cur == 0 {
                 continue;
             }
             while cur != all_pots {
                 // remaining == all the sets where the cur has been removed
-                // todo check lambda
-                let mut remaining: Vec<u16> = sets.iter()
-                    .map(|x| (x ^ cur) & x)
-                    .collect::<Vec<_>>();
+                for (r, x) in remaining.iter_mut().zip(sets) {
+                    *r = (x ^ cur) & x;
+                }
                 remaining.sort_by_key(|x| x.count_ones());
                 let counted = remaining.iter()
                     .take_while(|x| **x == 0).count();
@@ -38,183 +57,163 @@ impl Sudoku {
                     res |= cur;
                     break;
                 } else if counted > cur.count_ones() as usize {
-                    println!("{:?} -> {:?}", cur.get_pos(),
-                             remaining.iter().map(|r| r.get_pos()).collect::<Vec<_>>());
-                    panic!("something went wrong here...");
+                    // more cells than numbers to fill them with: the puzzle is broken,
+                    // cross out everything in the set and let is_valid report it
+                    res |= cur;
+                    break;
                 }
                 // add the smallest possible to the cur
                 cur |= remaining[counted];
             }
         }
-        return res;
+        res
     }
 
     /// Get all the entries that are taken, given as a single entrynum that combines them
-    fn get_taken(index: usize, to_consider: &Vec<usize>, data: &[u16; 81]) -> EntryNum {
+    fn get_taken(index: usize, to_consider: &[usize], data: &[EntryNum; 81]) -> EntryNum {
         let mut res: EntryNum = 0;
-        let mut sets = vec!();
+        let mut sets: [EntryNum; 9] = [0; 9];
+        let mut set_count = 0;
         for i in to_consider {
-            if i == &index {
+            if *i == index {
                 continue;
             }
-            let en = data[(*i) as usize];
+            let en = data[*i];
             if en.is_fixed() {
                 res |= en;
             } else {
-                sets.push(en);
+                sets[set_count] = en;
+                set_count += 1;
             }
         }
+        let sets = &sets[..set_count];
         // now to find the sets
-        let largest_missing: EntryNum = sets.iter().fold(0 as u16, |cur, ne| cur | *ne);
+        let largest_missing: EntryNum = sets.iter().fold(0, |cur, ne| cur | *ne);
         // order by increasing subsets
-        let taken_by_sets = Sudoku::get_sets(sets, largest_missing, data);
-//        println!("For {}, {:?} were taken by sets", index, taken_by_sets.get_pos());
-        res |= taken_by_sets;
-
+        res |= Sudoku::get_sets(sets, largest_missing);
         res
     }
 
 
-    fn eliminate_basic_possibilities(&mut self) -> u32 {
+    /// Cross out the numbers that are already fixed in the row, column or block of each cell
+    fn eliminate_fixed_peers(&mut self) -> u32 {
         let mut res = 0;
-        for i in 0..9 {
-            for j in 0..9 {
-                let index = i * 9 + j;
-                if self.data[index].is_fixed() {
-                    continue;
-                }
-                let col_nums = Sudoku::get_col_indices(index);
-                let row_nums = Sudoku::get_row_indices(index);
-                let box_nums = Sudoku::get_block_indices_by_cell_index(index);
-                let col_taken = Sudoku::get_taken(index, &col_nums, &self.data);
-                let row_taken = Sudoku::get_taken(index, &row_nums, &self.data);
-                let box_taken = Sudoku::get_taken(index, &box_nums, &self.data);
-                let all_taken = col_taken | row_taken | box_taken;
-                res += Sudoku::remove_possibilities(self.data.borrow_mut(), index, all_taken);
-                if self.data[index].is_fixed() {
-//                    println!("index {} was made fixed", index);
-                }
+        for index in 0..81 {
+            if self.data[index].is_fixed() {
+                continue;
             }
+            let taken = self.layout.peers(index).iter()
+                .map(|i| self.data[*i])
+                .filter(|en| en.is_fixed())
+                .fold(0, |cur, en| cur | en);
+            res += Sudoku::remove_possibilities(&mut self.data, index, taken);
         }
         res
     }
 
-    //    fn find_omissions(&mut self, nums_to_check : Vec<usize>)
-    const BLOCK_ONE: [usize; 3] = [0, 1, 2];
-    const BLOCK_TWO: [usize; 3] = [3, 4, 5];
-    const BLOCK_THREE: [usize; 3] = [6, 7, 8];
-    /// Find omissions in the sudoku
-    /// The gist of the concept is this:
-    /// when pencil marks in a row or column are contained inside a single block,
-    /// pencil marks elsewhere in the block can be removed.
-    fn eliminate_omissions(&mut self) -> u32 {
-        // go through each row & column (just row for now)
-        for i in 0..9 {
-            let row_nums = (0..9).map(|x| i * 9 + x).collect::<Vec<_>>();
-            let col_nums = (0..9).map(|x| x * 9 + i).collect::<Vec<_>>();
-            let block_row = i / 3;
-            let block_col = i % 3;
-            let block_nums = Sudoku::get_block_indices(block_row, block_col);
-            self.eliminate_omission(i, row_nums, EntityType::Row);
-            self.eliminate_omission(i, col_nums, EntityType::Col);
-            self.eliminate_omission(i, block_nums, EntityType::Box);
-        }
-        0
-    }
-
-    fn eliminate_omission(&mut self, rcb: usize, indices: Vec<usize>, t: EntityType) -> u32 {
-        let ens = indices.iter().map(|x| self.data[*x]).collect::<Vec<_>>();
-        // for each number, check where it is possible
+    /// Fill in a number when it has only one possible place left in a row, column or block
+    fn fill_hidden_singles(&mut self) -> u32 {
         let mut res = 0;
-        for i in 1..=9 {
-            let mask = entry_num::to_entry_num(i);
-            // get the positions where we are possible (and the related indices)
-            let mut pos_locs = ens.iter().enumerate()
-                .filter(|(i, x)| **x & mask > 0)
-                .collect::<Vec<_>>();
-
-            match pos_locs.len() {
-                // fill in an option when it's the only possible thing in the Row|Column|Block)
-                1 if !pos_locs[0].1.is_fixed() => self.data.borrow_mut()[indices[pos_locs[0].0]] = mask,
-                2 | 3 => { // we need to check whether these are in the same row|column|bloxk
-                    // so we can eliminate the rest
-                    let actual_indices: Vec<usize> = pos_locs.iter()
-                        .map(|(i, _v)| indices[*i]).collect::<Vec<_>>();
-                    let first_index = actual_indices[0];
-                    let to_remove: Vec<usize> = match t {
-                        // we get the indices from which we would want to remove (or none)
-                        EntityType::Row if Sudoku::in_same_RCB(&actual_indices, EntityType::Box) =>
-                            Sudoku::get_block_indices_by_cell_index(first_index),
-                        EntityType::Box if Sudoku::in_same_RCB(&actual_indices, EntityType::Row)
-                        => Sudoku::get_row_indices(first_index),
-                        EntityType::Box if Sudoku::in_same_RCB(&actual_indices, EntityType::Col) =>
-                            Sudoku::get_col_indices(first_index),
-                        EntityType::Col if Sudoku::in_same_RCB(&actual_indices, EntityType::Box) =>
-                            Sudoku::get_block_indices_by_cell_index(first_index),
-                        _ => vec!()
-                    };
-                    if to_remove.is_empty() { // continue on none
-                        continue;
+        for (_, unit) in self.layout.units() {
+            for num in 1..=9 {
+                let mask = entry_num::to_entry_num(num);
+                let mut places = unit.iter().filter(|x| self.data[**x] & mask > 0);
+                if let (Some(&only), None) = (places.next(), places.next()) {
+                    if !self.data[only].is_fixed() {
+                        res += Sudoku::remove_possibilities(&mut self.data, only, ALL & !mask);
                     }
-//                    println!("Removing {:?} at indices {:?}", mask.get_pos(), to_remove);
-                    let removal: u32 = to_remove.iter()
-                        .filter(|x| !actual_indices.contains(*x))
-                        .map(|x| Sudoku::remove_possibilities(self.data.borrow_mut(), *x, mask))
-                        .sum();
-                    res += removal;
                 }
-                _ => () // nothing to do in other cases, can't eliminate anything
             }
-//            if pos_locs.len() <= 3 { // possible to fit in a box
-//                // todo check that they are all in the same box
-//                // simple really; have to be contiguous and
-//                pos_locs.sort();
-//                let mut indices = pos_locs.iter().map(|(index, value)| indices[*index]).collect::<Vec<_>>();
-//                // check if we're a block
-//                let block_locs = pos_locs.iter().map(|(i, v)| *i).collect::<Vec<_>>();
-//                if block_locs == Sudoku::BLOCK_ONE || block_locs == Sudoku::BLOCK_TWO || block_locs == Sudoku::BLOCK_THREE {
-//                    res += self.remove_other_locs(rcb, mask, indices, block_locs);
-//                }
-//            }
         }
         res
     }
 
-    fn in_same_RCB(indices: &Vec<usize>, t: EntityType) -> bool {
-        let rcbs = match t {
-            EntityType::Row => indices.iter().map(|i| *i / 9).collect::<Vec<_>>(),
-            EntityType::Col => indices.iter().map(|i| *i % 9).collect::<Vec<_>>(),
-            EntityType::Box => indices.iter().map(|i| Sudoku::get_block_index(*i)).collect::<Vec<_>>()
-        };
-        let first = rcbs[0];
-        rcbs.iter().all(|x| *x == first)
+    /// Find omissions in the sudoku
+    /// The gist of the concept is this:
+    /// when pencil marks for a number in one unit are all contained inside another unit,
+    /// the number has to go in their overlap, so pencil marks elsewhere in the other unit can be removed.
+    /// For rows, columns and blocks these are the pointing pairs/triples (block inside a row or column)
+    /// and box/line reductions (row or column inside a block).
+    pub fn find_omissions(&self) -> Vec<Elimination> {
+        let units = self.layout.units();
+        let mut res = vec!();
+        for (u, (_, unit)) in units.iter().enumerate() {
+            for num in 1..=9 {
+                let mask = entry_num::to_entry_num(num);
+                // the positions in this unit where the number is still possible
+                let mut pos_locs: [usize; 9] = [0; 9];
+                let mut count = 0;
+                for x in unit.iter().filter(|x| self.data[**x] & mask > 0) {
+                    pos_locs[count] = *x;
+                    count += 1;
+                }
+                // a single position is a hidden single, nothing to point at
+                if count < 2 {
+                    continue;
+                }
+                let pos_locs = &pos_locs[..count];
+                let others = self.layout.cell_units(pos_locs[0]).iter()
+                    .filter(|other| **other != u)
+                    .filter(|other| pos_locs.iter().all(|x| self.layout.cell_units(*x).contains(other)));
+                for other in others {
+                    res.extend(units[*other].1.iter()
+                        .filter(|x| !pos_locs.contains(x) && self.data[**x] & mask > 0)
+                        .map(|x| Elimination { cell: *x, num }));
+                }
+            }
+        }
+        // a row and a column can point at the same cell of a block
+        res.sort();
+        res.dedup();
+        res
     }
 
-
-    fn remove_other_locs(&mut self, rcb_index: usize, mask: u16, indices: Vec<usize>, block_locs: Vec<usize>) -> u32 {
-        let block_row = rcb_index / 3;
-        let block_col = block_locs[0] / 3;
-// we need to remove the other possible numbers
-        let bloc_indices = Sudoku::get_block_indices(block_row, block_col);
-        let removed: u32 = bloc_indices.iter()
-            .filter(|x| !indices.contains(x))
-            .map(|x| Sudoku::remove_possibilities(self.data.borrow_mut(), *x, mask)).sum();
-        return removed;
+    /// Apply all the omissions found by `find_omissions`
+    /// Returns: the number of possibilities crossed out
+    fn eliminate_omissions(&mut self) -> u32 {
+        self.find_omissions().iter()
+            .map(|e| Sudoku::remove_possibilities(&mut self.data, e.cell, entry_num::to_entry_num(e.num)))
+            .sum()
     }
 }
 
-
 mod sudoku_get_indices;
+mod sudoku_search;
 
 
+#[derive(Clone)]
 pub struct Sudoku {
     data: [EntryNum; 81],
+    layout: Arc<Layout>,
+    constraints: Vec<Arc<dyn Constraint>>,
+    origins: [Origin; 81],
 }
 
 impl Display for Sudoku {
+    /// With `{:#}`, the givens are written in bold for terminals that understand ANSI escape codes
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
-        let content = data_to_string(&self.data);
-        write!(f, "{}", content)
+        if !f.alternate() {
+            return write!(f, "{}", data_to_string(&self.data));
+        }
+        for (i, en) in self.data.iter().enumerate() {
+            match self.origin(i) {
+                Some(Origin::Given) => write!(f, "\x1b[1m{}\x1b[0m", cell_to_string(*en))?,
+                _ => write!(f, "{}", cell_to_string(*en))?,
+            }
+            if i % 9 == 8 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The number of a fixed cell, or the list of its possibilities
+fn cell_to_string(en: EntryNum) -> String {
+    let pots = en.get_pos();
+    match pots.len() {
+        1 => format!("{}", pots[0]),
+        _ => format!("{:?}", pots),
     }
 }
 
@@ -224,13 +223,7 @@ fn data_to_string(data: &[u16; 81]) -> String {
 
     for i in 0..9 {
         for j in 0..9 {
-            let n = data[i * 9 + j];
-            let pots = n.get_pos();
-            let addition = match pots.len() {
-                1 => format!("{}", pots[0]),
-                _ => format!("{:?}", pots),
-            };
-            s += &addition;
+            s += &cell_to_string(data[i * 9 + j]);
         }
         s.push('\n');
     }
@@ -240,12 +233,20 @@ fn data_to_string(data: &[u16; 81]) -> String {
 #[cfg(test)]
 mod testing {
     use std::borrow::Borrow;
+    use std::cell::Cell;
     use std::error::Error;
     use std::fs::File;
     use std::io::{BufRead, BufReader};
+    use std::rc::Rc;
 
     use crate::sudokutwo::{data_to_string, Sudoku};
+    use crate::sudokutwo::solver_config::SolverConfig;
     use crate::sudokutwo::sudoku_api::SudokuApi;
+    use crate::sudokutwo::entry_num::{ALL, NINE, ONE};
+    use crate::sudokutwo::layout::Layout;
+    use crate::sudokutwo::origin::Origin;
+    use crate::sudokutwo::technique::{Elimination, NakedSingles, Technique};
+    use crate::sudokutwo::trace::Difficulty;
 
 //    const _BASIC_SUDOKU_DATA: [u16; 81] = [0, 0, 3, 0, 2, 0, 6, 0, 0, 9, 0, 0, 3, 0, 5, 0, 0, 1, 0, 0, 1, 8, 0, 6, 4, 0, 0, 0, 0, 8, 1, 0, 2, 9, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 8, 0, 0, 6, 7, 0, 8, 2, 0, 0, 0, 0, 2, 6, 0, 9, 5, 0, 0, 8, 0, 0, 2, 0, 3, 0, 0, 9, 0, 0, 5, 0, 1, 0, 3, 0, 0];
 
@@ -284,7 +285,7 @@ mod testing {
                 let before_unfixed = s.count_unfixed();
                 while s.solve_once() > 0 {
                     if let Err(msg) = s.is_valid() {
-                        panic!(msg);
+                        panic!("{}", msg);
                     }
                 }
                 assert_eq!(s.is_valid(), Ok(())); // even if not solved, it should be valid
@@ -296,7 +297,7 @@ mod testing {
             })
             .filter(Sudoku::is_solved)
             .count();
-        println!("Solved {} out of 50 -> {:.2}%", solved_count, 100 as f32 * solved_count as f32 / 50 as f32);
+        println!("Solved {} out of 50 -> {:.2}%", solved_count, 100.0 * solved_count as f32 / 50.0);
         println!("DONE ALL 50");
         Ok(())
     }
@@ -310,7 +311,7 @@ mod testing {
                 let before_unfixed = s.count_unfixed();
                 while s.solve_once() > 0 {
                     if let Err(msg) = s.is_valid() {
-                        panic!(msg);
+                        panic!("{}", msg);
                     }
                 }
                 assert_eq!(s.is_valid(), Ok(())); // even if not solved, it should be valid
@@ -321,7 +322,7 @@ mod testing {
             })
             .filter(Sudoku::is_solved)
             .count();
-        println!("Solved {} out of 95 -> {:.2}%", solved_count, 100 as f32 * solved_count as f32 / 95 as f32);
+        println!("Solved {} out of 95 -> {:.2}%", solved_count, 100.0 * solved_count as f32 / 95.0);
         println!("DONE ALL 95");
         Ok(())
     }
@@ -331,11 +332,104 @@ mod testing {
         let mut s = load_sudoku();
         while s.solve_once() > 0 {
             if let Err(msg) = s.is_valid() {
-                panic!(msg);
+                panic!("{}", msg);
             }
             println!("solving... \n{}", data_to_string(s.data.borrow()))
         }
         assert_eq!(s.is_valid(), Ok(()));
         println!("After: \n{}", data_to_string(s.data.borrow()));
     }
+
+    #[test]
+    fn singles_only_solves_easy() {
+        let mut s = load_sudoku();
+        SolverConfig::singles_only().run(&mut s);
+        assert!(s.is_solved());
+    }
+
+    #[test]
+    fn count_solutions() {
+        assert_eq!(load_sudoku().count_solutions(2), 1);
+        assert_eq!(Sudoku::new("0".repeat(81)).unwrap().count_solutions(2), 2);
+        let broken = Sudoku::new(format!("11{}", "0".repeat(79))).unwrap();
+        assert_eq!(broken.count_solutions(2), 0);
+    }
+
+    /// Counts how often it gets applied, without ever making progress
+    struct Counting(Rc<Cell<usize>>);
+
+    impl Technique for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn difficulty(&self) -> Difficulty {
+            Difficulty::Easy
+        }
+
+        fn apply(&self, _sudoku: &mut Sudoku) -> u32 {
+            self.0.set(self.0.get() + 1);
+            0
+        }
+    }
+
+    #[test]
+    fn restart_stops_at_first_progress() {
+        let mut plain = load_sudoku();
+        let first = NakedSingles.apply(&mut plain);
+        assert!(first > 0);
+        for (restart, runs) in [(true, 0), (false, 1)].iter() {
+            let count = Rc::new(Cell::new(0));
+            let config = SolverConfig::new(vec!(Box::new(NakedSingles), Box::new(Counting(count.clone()))), *restart);
+            let mut s = load_sudoku();
+            assert_eq!(s.solve_once_with(&config), first as usize);
+            assert_eq!(count.get(), *runs);
+        }
+    }
+
+
+    #[test]
+    fn pointing_pair() {
+        let mut data = [ALL; 81];
+        // within block 0, 1 is only possible in row 0
+        for i in [9, 10, 11, 18, 19, 20].iter() {
+            data[*i] &= !ONE;
+        }
+        let mut s = Sudoku { data, layout: Layout::standard(), constraints: vec!(), origins: [Origin::Logic; 81] };
+        let expected = (3..9).map(|cell| Elimination { cell, num: 1 }).collect::<Vec<_>>();
+        assert_eq!(s.find_omissions(), expected);
+        assert_eq!(s.eliminate_omissions(), 6);
+        assert!(s.find_omissions().is_empty());
+    }
+
+    #[test]
+    fn box_line_reduction() {
+        let mut data = [ALL; 81];
+        // within row 0, 9 is only possible in block 0
+        for en in data.iter_mut().take(9).skip(3) {
+            *en &= !NINE;
+        }
+        let s = Sudoku { data, layout: Layout::standard(), constraints: vec!(), origins: [Origin::Logic; 81] };
+        let expected = [9, 10, 11, 18, 19, 20].iter()
+            .map(|cell| Elimination { cell: *cell, num: 9 })
+            .collect::<Vec<_>>();
+        assert_eq!(s.find_omissions(), expected);
+    }
+
+    #[test]
+    fn expert_solves_what_default_solves() -> Result<(), Box<dyn Error>> {
+        let expert = SolverConfig::expert();
+        let default = SolverConfig::default();
+        let file = File::open("resources/sudoku-easy-50.txt")?;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let mut s = Sudoku::new(line.clone())?;
+            default.run(&mut s);
+            let mut e = Sudoku::new(line)?;
+            expert.run(&mut e);
+            assert_eq!(e.is_valid(), Ok(()));
+            assert!(e.is_solved() || !s.is_solved(), "expert could not solve\n{}", e);
+        }
+        Ok(())
+    }
 }
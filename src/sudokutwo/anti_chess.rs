@@ -0,0 +1,160 @@
+use std::fmt::{Display, Error, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::constraint::{cell_name, Constraint, cross_out};
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{EntryNum, EntryNumThings};
+
+/// The move of a chess piece, as used by the anti-knight and anti-king rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChessMove {
+    Knight,
+    /// Only the diagonal steps: the others stay in the same row or column anyway
+    King,
+}
+
+impl ChessMove {
+    fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            ChessMove::Knight => &[(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)],
+            ChessMove::King => &[(-1, -1), (-1, 1), (1, -1), (1, 1)],
+        }
+    }
+
+    /// All the cells one move away from the cell
+    pub fn peers(self, cell: usize) -> Vec<usize> {
+        let (row, col) = ((cell / 9) as isize, (cell % 9) as isize);
+        self.offsets().iter()
+            .map(|(dr, dc)| (row + dr, col + dc))
+            .filter(|(r, c)| (0..9).contains(r) && (0..9).contains(c))
+            .map(|(r, c)| (r * 9 + c) as usize)
+            .collect()
+    }
+}
+
+impl Display for ChessMove {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            ChessMove::Knight => write!(f, "knight's move"),
+            ChessMove::King => write!(f, "king's move"),
+        }
+    }
+}
+
+/// Cells a chess move apart can't hold the same number.
+/// The move peers are extra peers of every cell, next to its row, column and block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AntiMove {
+    chess_move: ChessMove,
+    peers: Vec<Vec<usize>>,
+}
+
+impl AntiMove {
+    pub fn new(chess_move: ChessMove) -> Self {
+        AntiMove { chess_move, peers: (0..81).map(|cell| chess_move.peers(cell)).collect() }
+    }
+
+    /// Cells a knight's move apart can't hold the same number
+    pub fn knight() -> Self {
+        AntiMove::new(ChessMove::Knight)
+    }
+
+    /// Diagonally adjacent cells can't hold the same number
+    pub fn king() -> Self {
+        AntiMove::new(ChessMove::King)
+    }
+
+    pub fn chess_move(&self) -> ChessMove {
+        self.chess_move
+    }
+
+    pub fn peers(&self, cell: usize) -> &[usize] {
+        &self.peers[cell]
+    }
+}
+
+impl Constraint for AntiMove {
+    /// Cross out the number of every fixed cell from its move peers
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let mut res = 0;
+        for cell in 0..81 {
+            if data[cell].is_fixed() {
+                continue;
+            }
+            let taken = self.peers[cell].iter()
+                .map(|i| data[*i])
+                .filter(|en| en.is_fixed())
+                .fold(0, |cur, en| cur | en);
+            res += cross_out(data, cell, taken)?;
+        }
+        self.check(data)?;
+        Ok(res)
+    }
+
+    fn check(&self, data: &[EntryNum; 81]) -> Result<(), String> {
+        for (cell, peers) in self.peers.iter().enumerate().filter(|(cell, _)| data[*cell].is_fixed()) {
+            if let Some(other) = peers.iter().find(|i| data[**i] == data[cell]) {
+                return Err(format!("{} and {} are a {} apart and both hold {}",
+                                   cell_name(cell), cell_name(*other), self.chess_move, data[cell].get_fixed()));
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.anti_chess.push(self.chess_move);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::sudokutwo::anti_chess::{AntiMove, ChessMove};
+    use crate::sudokutwo::constraint::Constraint;
+    use crate::sudokutwo::entry_num::{ALL, EntryNumThings, FIVE};
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    #[test]
+    fn move_peers() {
+        assert_eq!(ChessMove::Knight.peers(40).len(), 8);
+        assert_eq!(ChessMove::Knight.peers(0), vec!(11, 19));
+        assert_eq!(ChessMove::King.peers(0), vec!(10));
+        assert_eq!(ChessMove::King.peers(40), vec!(30, 32, 48, 50));
+    }
+
+    #[test]
+    fn prune_knight() {
+        let mut data = [ALL; 81];
+        data[0] = FIVE;
+        assert_eq!(AntiMove::knight().prune(&mut data), Ok(2));
+        assert!(!data[11].get_pos().contains(&5));
+        assert!(!data[19].get_pos().contains(&5));
+        assert_eq!(data[1], ALL);
+    }
+
+    #[test]
+    fn repeat_a_move_apart() {
+        let mut data = [ALL; 81];
+        data[0] = FIVE;
+        data[10] = FIVE;
+        assert_eq!(AntiMove::king().check(&data),
+                   Err(String::from("r1c1 and r2c2 are a king's move apart and both hold 5")));
+        assert_eq!(AntiMove::knight().check(&data), Ok(()));
+    }
+
+    #[test]
+    fn solve_with_chess_moves() {
+        for rule in [AntiMove::knight(), AntiMove::king()].iter() {
+            let mut s = Sudoku::new("0".repeat(81)).unwrap();
+            s.add_constraint(Arc::new(rule.clone()));
+            assert!(s.attempt_solve());
+            assert!(s.is_solved());
+            assert_eq!(rule.check(&s.data), Ok(()));
+        }
+    }
+}
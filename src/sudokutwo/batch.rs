@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Error, Formatter};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+/// How many lines can wait in the queue per thread, so the input isn't read much further ahead than the solvers
+const QUEUED_PER_THREAD: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// A complete, valid solution was found
+    Solved,
+    /// The puzzle could be read, but has no solution
+    Unsolvable,
+    /// The line is not a puzzle
+    Invalid,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let s = match self {
+            Status::Solved => "solved",
+            Status::Unsolvable => "unsolvable",
+            Status::Invalid => "invalid",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The outcome of solving a single line of a batch
+#[derive(Debug, Clone)]
+pub struct BatchResult {
+    /// The solution as a line, or None when the puzzle was not solved
+    pub solution: Option<String>,
+    pub status: Status,
+    pub duration: Duration,
+}
+
+impl Display for BatchResult {
+    /// Formats as `solution,status,microseconds`, leaving the solution empty when there is none
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{},{},{}", self.solution.as_deref().unwrap_or(""), self.status, self.duration.as_micros())
+    }
+}
+
+/// Solve a single puzzle line
+pub fn solve_line(line: &str) -> BatchResult {
+    let start = Instant::now();
+    let (solution, status) = match Sudoku::new(line.trim().to_string()) {
+        Err(_) => (None, Status::Invalid),
+        Ok(mut s) => {
+            if s.attempt_solve() {
+                (Some(s.to_line()), Status::Solved)
+            } else {
+                (None, Status::Unsolvable)
+            }
+        }
+    };
+    BatchResult { solution, status, duration: start.elapsed() }
+}
+
+/// Solve all the given lines, spread over the given number of threads.
+/// Every thread takes the next line as soon as it is done with the last, so one slow puzzle doesn't hold up the rest.
+/// Returns: one result per line, in the same order as the lines
+pub fn solve_lines(lines: &[String], threads: usize) -> Vec<BatchResult> {
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let handles = (0..threads.max(1))
+            .map(|_| scope.spawn(|| {
+                let mut res = vec!();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    match lines.get(index) {
+                        Some(line) => res.push((index, solve_line(line))),
+                        None => return res,
+                    }
+                }
+            }))
+            .collect::<Vec<_>>();
+        handles.into_iter()
+            .flat_map(|h| h.join().expect("solver thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Write the results that are next in line, keeping the rest until the ones before them are done
+fn write_ready<W: Write>(output: &mut W, pending: &mut BTreeMap<usize, BatchResult>, written: &mut usize) -> io::Result<()> {
+    while let Some(result) = pending.remove(written) {
+        writeln!(output, "{}", result)?;
+        *written += 1;
+    }
+    Ok(())
+}
+
+/// Read puzzle lines from the input, solve them over the given number of threads,
+/// and write one result line per puzzle to the output, in input order. Blank lines are skipped.
+/// The threads take lines from a queue while the input is still being read, and results are written as soon as
+/// every puzzle before them is done.
+/// Returns: the number of puzzles processed
+pub fn solve_stream<R: BufRead, W: Write>(input: R, mut output: W, threads: usize) -> io::Result<usize> {
+    let threads = threads.max(1);
+    let (job_sender, jobs) = mpsc::sync_channel::<(usize, String)>(threads * QUEUED_PER_THREAD);
+    let jobs = Mutex::new(jobs);
+    let (result_sender, results) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let result_sender = result_sender.clone();
+            let jobs = &jobs;
+            scope.spawn(move || loop {
+                let job = jobs.lock().expect("job queue poisoned").recv();
+                match job {
+                    Ok((index, line)) => {
+                        if result_sender.send((index, solve_line(&line))).is_err() {
+                            return;
+                        }
+                    }
+                    Err(_) => return,
+                }
+            });
+        }
+        drop(result_sender);
+        let mut pending = BTreeMap::new();
+        let mut written = 0;
+        let mut total = 0;
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            job_sender.send((total, line)).expect("solver threads stopped");
+            total += 1;
+            pending.extend(results.try_iter());
+            write_ready(&mut output, &mut pending, &mut written)?;
+        }
+        drop(job_sender);
+        for (index, result) in results.iter() {
+            pending.insert(index, result);
+            write_ready(&mut output, &mut pending, &mut written)?;
+        }
+        output.flush()?;
+        Ok(total)
+    })
+}
+
+/// The number of threads to use when none is given: one per core
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    use crate::sudokutwo::batch::{solve_line, solve_lines, solve_stream, Status};
+
+    #[test]
+    fn bad_lines() {
+        assert_eq!(solve_line("12345").status, Status::Invalid);
+        // 81 bytes, but the last one is an Arabic-Indic three
+        assert_eq!(solve_line(&format!("{}\u{0663}", "0".repeat(79))).status, Status::Invalid);
+        // two 1s in the first row
+        let line = format!("11{}", "0".repeat(79));
+        assert_eq!(solve_line(&line).status, Status::Unsolvable);
+    }
+
+    #[test]
+    fn top_95_in_order() {
+        let puzzles = BufReader::new(File::open("resources/top-95.txt").unwrap())
+            .lines().map(|l| l.unwrap()).collect::<Vec<_>>();
+        let mut out = vec!();
+        // blank lines in between and at the end are skipped
+        let input = format!("{}\n\n{}\n", puzzles[..50].join("\n"), puzzles[50..].join("\n"));
+        let count = solve_stream(input.as_bytes(), &mut out, 4).unwrap();
+        assert_eq!(count, 95);
+        let results = String::from_utf8(out).unwrap();
+        let results = results.lines().collect::<Vec<_>>();
+        assert_eq!(results.len(), 95);
+        for (puzzle, result) in puzzles.iter().zip(results) {
+            let fields = result.split(',').collect::<Vec<_>>();
+            assert_eq!(fields[1], "solved");
+            // every given is kept in the solution
+            assert!(puzzle.chars().zip(fields[0].chars()).all(|(p, s)| p == '0' || p == s));
+        }
+    }
+
+    #[test]
+    fn lines_in_order() {
+        let lines = vec!(format!("11{}", "0".repeat(79)), String::from("x"), "0".repeat(81));
+        let statuses = solve_lines(&lines, 2).iter().map(|r| r.status).collect::<Vec<_>>();
+        assert_eq!(statuses, vec!(Status::Unsolvable, Status::Invalid, Status::Solved));
+    }
+}
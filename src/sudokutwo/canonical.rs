@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::layout::Layout;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+const ORDERS: [[usize; 3]; 6] = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+
+/// Every order of the columns that keeps the stacks together: 6 orders of the stacks, and 6 within each stack
+fn column_orders() -> Vec<[usize; 9]> {
+    let mut res = vec!();
+    for stacks in ORDERS.iter() {
+        for a in ORDERS.iter() {
+            for b in ORDERS.iter() {
+                for c in ORDERS.iter() {
+                    let within = [a, b, c];
+                    let mut order = [0; 9];
+                    for (i, col) in order.iter_mut().enumerate() {
+                        *col = stacks[i / 3] * 3 + within[i / 3][i % 3];
+                    }
+                    res.push(order);
+                }
+            }
+        }
+    }
+    res
+}
+
+/// A canonical form in the making: which grid it reads from, the rows it picked so far,
+/// the order of the columns, and the new label of every number that showed up already
+#[derive(Clone, Copy)]
+struct Candidate {
+    transposed: bool,
+    used_rows: u16,
+    last_row: usize,
+    cols: [usize; 9],
+    labels: [u8; 10],
+    next_label: u8,
+}
+
+impl Candidate {
+    /// The rows that can come next: any row of an unused band when a new band starts,
+    /// otherwise the unused rows of the current band
+    fn next_rows(&self, depth: usize) -> Vec<usize> {
+        (0..9)
+            .filter(|row| self.used_rows & (1 << row) == 0)
+            .filter(|row| if depth % 3 == 0 {
+                self.used_rows & (0b111 << (row / 3 * 3)) == 0
+            } else {
+                row / 3 == self.last_row / 3
+            })
+            .collect()
+    }
+
+    /// Add a row to the form, handing out the next labels to numbers that show up for the first time
+    fn with_row(&self, grid: &[u8; 81], row: usize) -> (Candidate, [u8; 9]) {
+        let mut next = *self;
+        next.used_rows |= 1 << row;
+        next.last_row = row;
+        let mut res = [0; 9];
+        for (out, col) in res.iter_mut().zip(self.cols.iter()) {
+            let n = grid[row * 9 + col] as usize;
+            if n == 0 {
+                continue;
+            }
+            if next.labels[n] == 0 {
+                next.labels[n] = next.next_label;
+                next.next_label += 1;
+            }
+            *out = next.labels[n];
+        }
+        (next, res)
+    }
+}
+
+/// The canonical form of a grid of givens, with 0 for an empty cell.
+/// It is the smallest grid, read row by row, that the givens can be turned into
+/// by relabelling the numbers, swapping rows within a band, swapping bands, doing the same for columns and stacks,
+/// and transposing. Those keep a sudoku valid, so two puzzles are the same puzzle in disguise
+/// exactly when their canonical forms match.
+///
+/// The form is built one row at a time, keeping only the ways to get the smallest rows so far.
+pub fn canonical_form(givens: &[u8; 81]) -> [u8; 81] {
+    let mut transposed = [0; 81];
+    for (i, n) in transposed.iter_mut().enumerate() {
+        *n = givens[i % 9 * 9 + i / 9];
+    }
+    let mut candidates = vec!();
+    for grid in [false, true].iter() {
+        for cols in column_orders() {
+            candidates.push(Candidate { transposed: *grid, used_rows: 0, last_row: 0, cols, labels: [0; 10], next_label: 1 });
+        }
+    }
+    let mut res = [0; 81];
+    for depth in 0..9 {
+        let mut best: Option<[u8; 9]> = None;
+        let mut next = vec!();
+        let mut seen = HashSet::new();
+        for candidate in candidates.iter() {
+            let grid = if candidate.transposed { &transposed } else { givens };
+            for row in candidate.next_rows(depth) {
+                let (extended, numbers) = candidate.with_row(grid, row);
+                match best {
+                    Some(smallest) if numbers > smallest => continue,
+                    Some(smallest) if numbers == smallest => {}
+                    _ => {
+                        best = Some(numbers);
+                        next.clear();
+                        seen.clear();
+                    }
+                }
+                // the order of the rows picked so far doesn't matter for the rows still to come
+                if seen.insert((extended.transposed, extended.used_rows, extended.cols, extended.labels)) {
+                    next.push(extended);
+                }
+            }
+        }
+        res[depth * 9..depth * 9 + 9].copy_from_slice(&best.expect("there is always a row left to pick"));
+        candidates = next;
+    }
+    res
+}
+
+impl Sudoku {
+    /// The fixed numbers of the puzzle in canonical form, see `canonical_form`
+    /// Returns: the form as a line, or an error if the puzzle has other units or variant rules,
+    /// as the transformations don't keep those intact
+    pub fn canonical_form(&self) -> Result<String, String> {
+        if *self.layout != *Layout::standard() || !self.constraints.is_empty() {
+            return Err(String::from("Only standard sudokus without variant rules have a canonical form"));
+        }
+        let mut givens = [0; 81];
+        for (n, c) in givens.iter_mut().zip(self.to_line().bytes()) {
+            *n = c - b'0';
+        }
+        Ok(canonical_form(&givens).iter().map(|n| (b'0' + n) as char).collect())
+    }
+
+    /// Returns true iff. the puzzles are the same up to relabelling, shuffling and transposing
+    pub fn is_equivalent(&self, other: &Sudoku) -> Result<bool, String> {
+        Ok(self.canonical_form()? == other.canonical_form()?)
+    }
+}
+
+/// Group the puzzles of a catalogue that are the same puzzle in disguise
+/// Returns: the line indices of every different puzzle, in order of first appearance,
+/// or an error for the first line that isn't a puzzle
+pub fn group_equivalent(lines: &[String]) -> Result<Vec<Vec<usize>>, String> {
+    let mut groups: Vec<Vec<usize>> = vec!();
+    let mut by_form = HashMap::new();
+    for (i, line) in lines.iter().enumerate() {
+        let form = Sudoku::new(line.clone())
+            .and_then(|sudoku| sudoku.canonical_form())
+            .map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        let group = *by_form.entry(form).or_insert_with(|| {
+            groups.push(vec!());
+            groups.len() - 1
+        });
+        groups[group].push(i);
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    use crate::sudokutwo::canonical::group_equivalent;
+    use crate::sudokutwo::layout::Layout;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+
+    /// `EASY` transposed, with the first two bands and the last two columns swapped and every number n written as 10 - n
+    fn disguised() -> String {
+        let easy = EASY.as_bytes();
+        (0..81)
+            .map(|i| {
+                let (row, col) = (i / 9, i % 9);
+                let row = if row < 6 { (row + 3) % 6 } else { row };
+                let col = match col { 7 => 8, 8 => 7, c => c };
+                match easy[col * 9 + row] {
+                    b'0' => '0',
+                    c => (b'0' + 10 - (c - b'0')) as char,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn disguised_puzzle_is_equivalent() {
+        let easy = Sudoku::new(String::from(EASY)).unwrap();
+        let other = Sudoku::new(disguised()).unwrap();
+        assert_ne!(easy.to_line(), other.to_line());
+        assert_eq!(easy.canonical_form(), other.canonical_form());
+        assert_eq!(easy.is_equivalent(&other), Ok(true));
+
+        // moving a single given makes it a different puzzle
+        let moved = format!("3{}", &EASY[..2]) + &EASY[3..];
+        assert_eq!(easy.is_equivalent(&Sudoku::new(moved).unwrap()), Ok(false));
+    }
+
+    #[test]
+    fn solved_grid_starts_counting() {
+        let mut sudoku = Sudoku::new(String::from(EASY)).unwrap();
+        assert!(sudoku.attempt_solve());
+        let form = sudoku.canonical_form().unwrap();
+        assert!(form.starts_with("123456789"));
+        assert!(Sudoku::new(form).unwrap().is_solved());
+        let mut empty = Sudoku::new("0".repeat(81)).unwrap();
+        assert_eq!(empty.canonical_form(), Ok("0".repeat(81)));
+        empty.layout = Layout::diagonal();
+        assert!(empty.canonical_form().is_err());
+    }
+
+    #[test]
+    fn group_catalogue() {
+        let mut lines = BufReader::new(File::open("resources/top-95.txt").unwrap())
+            .lines().map(|l| l.unwrap()).take(10).collect::<Vec<_>>();
+        lines.push(disguised());
+        lines.push(String::from(EASY));
+        let groups = group_equivalent(&lines).unwrap();
+        assert_eq!(groups.len(), 11);
+        assert_eq!(groups[10], vec!(10, 11));
+        assert!(group_equivalent(&[String::from("12345")]).is_err());
+    }
+}
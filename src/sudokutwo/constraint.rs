@@ -0,0 +1,254 @@
+use std::sync::Arc;
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{self, ALL, EntryNum, EntryNumThings};
+use crate::sudokutwo::layout::Unit;
+
+/// A rule the numbers in the grid have to follow.
+/// The units of the layout are `AllDifferent` constraints, and variant rules (cages, ...) are added
+/// to a sudoku with `Sudoku::add_constraint` without touching the solver itself.
+pub trait Constraint: Send + Sync {
+    /// Cross out the possibilities that would break the constraint
+    /// Returns: the number of possibilities crossed out, or an error when the constraint can't be met anymore
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String>;
+
+    /// Check that the grid can still meet the constraint, without changing it
+    fn check(&self, data: &[EntryNum; 81]) -> Result<(), String> {
+        let mut copy = *data;
+        self.prune(&mut copy).map(|_| ())
+    }
+
+    /// Write the rule down in the description, so the puzzle can be saved and created again
+    /// Returns: an error if descriptions have no place for the rule
+    fn describe(&self, _description: &mut Description) -> Result<(), String> {
+        Err(String::from("The puzzle has a rule that can't be written down"))
+    }
+}
+
+/// The name of a cell as used in puzzle descriptions, `r<row>c<col>` (1-based)
+pub fn cell_name(cell: usize) -> String {
+    format!("r{}c{}", cell / 9 + 1, cell % 9 + 1)
+}
+
+/// Parse a cell written as `r<row>c<col>` (1-based), for example `r1c9`
+pub fn parse_cell(cell: &str) -> Option<usize> {
+    let (row, col) = cell.strip_prefix('r')?.split_once('c')?;
+    let (row, col) = (row.parse::<usize>().ok()?, col.parse::<usize>().ok()?);
+    if (1..=9).contains(&row) && (1..=9).contains(&col) {
+        Some((row - 1) * 9 + col - 1)
+    } else {
+        None
+    }
+}
+
+/// Parse a list of cells written as `r<row>c<col>`
+pub fn parse_cells<S: AsRef<str>>(names: &[S]) -> Result<Vec<usize>, String> {
+    names.iter()
+        .map(|name| parse_cell(name.as_ref()).ok_or_else(|| format!("'{}' is not a cell", name.as_ref())))
+        .collect()
+}
+
+/// Cross out possibilities of a cell on behalf of a constraint
+/// Returns: the number of possibilities crossed out, or an error when the cell has none left
+pub fn cross_out(data: &mut [EntryNum; 81], cell: usize, to_remove: EntryNum) -> Result<u32, String> {
+    let removed = Sudoku::remove_possibilities(data, cell, to_remove);
+    if data[cell] == 0 {
+        return Err(format!("At {}, there are no possibilities left", cell_name(cell)));
+    }
+    Ok(removed)
+}
+
+/// Prune cells whose numbers (which may repeat) add up to a total between `total_low` and `total_high`:
+/// every cell has to leave room for the others to reach the total
+/// Returns: the number of possibilities crossed out, or an error when a cell has none left
+pub fn prune_sum(data: &mut [EntryNum; 81], cells: &[usize], total_low: u16, total_high: u16) -> Result<u32, String> {
+    // no cell can be empty, so every cell has a lowest and highest possibility
+    for cell in cells {
+        cross_out(data, *cell, 0)?;
+    }
+    let low = |en: EntryNum| en.lowest().unwrap_or(0);
+    let high = |en: EntryNum| en.highest().unwrap_or(0);
+    let min_sum: u16 = cells.iter().map(|i| low(data[*i])).sum();
+    let max_sum: u16 = cells.iter().map(|i| high(data[*i])).sum();
+    let mut res = 0;
+    for cell in cells {
+        let others_min = min_sum - low(data[*cell]);
+        let others_max = max_sum - high(data[*cell]);
+        let keep = entry_num::range(total_low.saturating_sub(others_max), total_high.saturating_sub(others_min));
+        res += cross_out(data, *cell, ALL & !keep)?;
+    }
+    Ok(res)
+}
+
+/// Keep only the numbers in two cells that have a partner in the other cell for which `related` holds
+/// Returns: the number of possibilities crossed out, or an error when a cell has none left
+pub fn prune_pair<F>(data: &mut [EntryNum; 81], a: usize, b: usize, related: F) -> Result<u32, String>
+    where F: Fn(u16, u16) -> bool {
+    let supported = |x: EntryNum, y: EntryNum, swap: bool| x.get_pos().into_iter()
+        .filter(|n| y.get_pos().into_iter().any(|m| if swap { related(m, *n) } else { related(*n, m) }))
+        .fold(0, |cur, n| cur | entry_num::to_entry_num(n));
+    let keep_a = supported(data[a], data[b], false);
+    let mut res = cross_out(data, a, ALL & !keep_a)?;
+    let keep_b = supported(data[b], data[a], true);
+    res += cross_out(data, b, ALL & !keep_b)?;
+    Ok(res)
+}
+
+/// Every number appears exactly once in the cells of a unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllDifferent {
+    pub unit: Unit,
+    pub cells: [usize; 9],
+}
+
+impl AllDifferent {
+    pub fn new(unit: Unit, cells: [usize; 9]) -> Self {
+        AllDifferent { unit, cells }
+    }
+
+    /// No fixed number appears twice, and every number still has a place
+    fn check_places(&self, data: &[EntryNum; 81]) -> Result<(), String> {
+        let mut fixed: EntryNum = 0;
+        let mut possible: EntryNum = 0;
+        for en in self.cells.iter().map(|i| data[*i]) {
+            if en.is_fixed() {
+                if fixed & en > 0 {
+                    return Err(format!("{} appears more than once in {}", en.get_fixed(), self.unit));
+                }
+                fixed |= en;
+            }
+            possible |= en;
+        }
+        if possible != ALL {
+            let missing = (ALL & !possible).trailing_zeros() + 1;
+            return Err(format!("{} is not possible in {}", missing, self.unit));
+        }
+        Ok(())
+    }
+}
+
+impl Constraint for AllDifferent {
+    /// Cross out the numbers taken by fixed cells and by sets of cells (naked pairs, triples, ...)
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let mut res = 0;
+        for cell in self.cells.iter() {
+            if data[*cell].is_fixed() {
+                continue;
+            }
+            let taken = Sudoku::get_taken(*cell, &self.cells, data);
+            res += cross_out(data, *cell, taken)?;
+        }
+        self.check_places(data)?;
+        Ok(res)
+    }
+
+    fn check(&self, data: &[EntryNum; 81]) -> Result<(), String> {
+        self.check_places(data)
+    }
+}
+
+impl Sudoku {
+    /// Add a variant rule on top of the units of the layout
+    pub fn add_constraint(&mut self, constraint: Arc<dyn Constraint>) {
+        self.constraints.push(constraint);
+    }
+
+    /// The variant rules added on top of the units of the layout
+    pub fn constraints(&self) -> &[Arc<dyn Constraint>] {
+        &self.constraints
+    }
+
+    /// Prune every unit of the layout as an `AllDifferent` constraint
+    /// Returns: the number of possibilities crossed out
+    pub(crate) fn eliminate_basic_possibilities(&mut self) -> u32 {
+        let mut res = 0;
+        for (unit, cells) in self.layout.units() {
+            match AllDifferent::new(*unit, *cells).prune(&mut self.data) {
+                Ok(removed) => res += removed,
+                // leave the contradiction for is_valid to report
+                Err(_) => break,
+            }
+        }
+        res
+    }
+
+    /// Prune every variant rule once
+    /// Returns: the number of possibilities crossed out
+    pub(crate) fn apply_constraints(&mut self) -> u32 {
+        let mut res = 0;
+        for constraint in &self.constraints {
+            match constraint.prune(&mut self.data) {
+                Ok(removed) => res += removed,
+                Err(_) => break,
+            }
+        }
+        res
+    }
+
+    /// Check the units of the layout, then every variant rule
+    pub(crate) fn check_constraints(&self) -> Result<(), String> {
+        for (unit, cells) in self.layout.units() {
+            AllDifferent::new(*unit, *cells).check(&self.data)?;
+        }
+        for constraint in &self.constraints {
+            constraint.check(&self.data)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::sudokutwo::constraint::{AllDifferent, Constraint, cross_out};
+    use crate::sudokutwo::entry_num::{ALL, EntryNum, EntryNumThings, EVEN, FIVE, ONE, THREE, TWO};
+    use crate::sudokutwo::layout::Unit;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    /// The top left cell holds an odd number
+    struct OddCorner;
+
+    impl Constraint for OddCorner {
+        fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+            cross_out(data, 0, EVEN)
+        }
+    }
+
+    #[test]
+    fn all_different_pair() {
+        let mut data = [ALL; 81];
+        data[0] = ONE | TWO;
+        data[1] = ONE | TWO;
+        data[2] = THREE;
+        let row = AllDifferent::new(Unit::Row(0), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(row.prune(&mut data), Ok(18));
+        assert_eq!(data[3].get_pos(), vec!(4, 5, 6, 7, 8, 9));
+        assert_eq!(data[9], ALL);
+    }
+
+    #[test]
+    fn all_different_repeat() {
+        let mut data = [ALL; 81];
+        data[0] = FIVE;
+        data[4] = FIVE;
+        let row = AllDifferent::new(Unit::Row(0), [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(row.check(&data), Err(String::from("5 appears more than once in r1")));
+        assert!(row.prune(&mut data).is_err());
+    }
+
+    #[test]
+    fn plugged_in_constraint() {
+        let mut s = Sudoku::new("0".repeat(81)).unwrap();
+        s.add_constraint(Arc::new(OddCorner));
+        assert!(s.attempt_solve());
+        assert_eq!(s.data[0].get_fixed() % 2, 1);
+
+        let mut s = Sudoku::new(format!("2{}", "0".repeat(80))).unwrap();
+        assert_eq!(s.is_valid(), Ok(()));
+        s.add_constraint(Arc::new(OddCorner));
+        assert_eq!(s.is_valid(), Err(String::from("At r1c1, there are no possibilities left")));
+    }
+}
@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::anti_chess::{AntiMove, ChessMove};
+use crate::sudokutwo::constraint::Constraint;
+use crate::sudokutwo::dots::{GreaterThan, Kropki, ParityCell, Xv};
+use crate::sudokutwo::killer::{Cage, check_cages, FortyFiveRule};
+use crate::sudokutwo::layout::{Layout, Unit};
+use crate::sudokutwo::lines::{Arrow, Palindrome, Renban, Thermometer, Whispers};
+use crate::sudokutwo::outside::{LittleKiller, Sandwich};
+
+/// A puzzle with its variant rules, in a compact JSON format with cells written as `r<row>c<col>`:
+/// ```json
+/// {
+///   "givens": "000000000...",
+///   "layout": "diagonal",
+///   "cages": ["15=r1c1 r1c2 r2c1"],
+///   "anti_chess": ["knight", "king"],
+///   "thermometers": [["r1c1", "r1c2", "r1c3"]],
+///   "arrows": [["r5c5", "r4c4", "r3c3"]],
+///   "whispers": [["r7c1", "r8c2", "r9c3"]],
+///   "renban": [["r1c9", "r2c9", "r3c9"]],
+///   "palindromes": [["r4c1", "r5c2", "r6c1"]],
+///   "kropki": [{"white": ["r1c1", "r2c1"]}, {"black": ["r9c8", "r9c9"]}],
+///   "parity": [{"even": "r1c1"}, {"odd": "r5c5"}],
+///   "greater_than": [["r1c1", "r1c2"]],
+///   "xv": [{"x": ["r2c1", "r2c2"]}, {"v": ["r3c1", "r3c2"]}],
+///   "sandwiches": ["16=r1", "0=c5"],
+///   "little_killers": ["37=r1c2 se"]
+/// }
+/// ```
+/// The layout is left out for standard sudokus, and is `"diagonal"`, `"windoku"` or `{"jigsaw": "<region map>"}` otherwise.
+/// Thermometers start at the bulb, arrows start at the circle, greater-than signs start at the greater cell.
+/// Outside clues are written like cages: sandwiches as `<sum>=<row or column>`,
+/// little killers as `<sum>=<first cell> <direction>`. Rules without any entries can be left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Description {
+    pub givens: String,
+    #[serde(default, skip_serializing_if = "LayoutDescription::is_standard")]
+    pub layout: LayoutDescription,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cages: Vec<Cage>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anti_chess: Vec<ChessMove>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thermometers: Vec<Thermometer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arrows: Vec<Arrow>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub whispers: Vec<Whispers>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renban: Vec<Renban>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub palindromes: Vec<Palindrome>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kropki: Vec<Kropki>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parity: Vec<ParityCell>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub greater_than: Vec<GreaterThan>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub xv: Vec<Xv>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sandwiches: Vec<Sandwich>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub little_killers: Vec<LittleKiller>,
+}
+
+/// The units of a puzzle, as written in a description
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayoutDescription {
+    #[default]
+    Standard,
+    Diagonal,
+    Windoku,
+    /// The region map, as for `Layout::jigsaw`
+    Jigsaw(String),
+}
+
+impl LayoutDescription {
+    pub fn is_standard(&self) -> bool {
+        *self == LayoutDescription::Standard
+    }
+
+    pub fn to_layout(&self) -> Result<Arc<Layout>, String> {
+        match self {
+            LayoutDescription::Standard => Ok(Layout::standard()),
+            LayoutDescription::Diagonal => Ok(Layout::diagonal()),
+            LayoutDescription::Windoku => Ok(Layout::windoku()),
+            LayoutDescription::Jigsaw(region_map) => Layout::jigsaw(region_map).map(Arc::new),
+        }
+    }
+
+    /// Recognise the layout, writing jigsaw regions as a map with the letters A to I
+    /// Returns: an error for other layouts
+    pub fn from_layout(layout: &Layout) -> Result<Self, String> {
+        let known = [
+            (Layout::standard(), LayoutDescription::Standard),
+            (Layout::diagonal(), LayoutDescription::Diagonal),
+            (Layout::windoku(), LayoutDescription::Windoku),
+        ];
+        if let Some((_, description)) = known.iter().find(|(known, _)| **known == *layout) {
+            return Ok(description.clone());
+        }
+        let mut region_map = ['?'; 81];
+        for (unit, cells) in layout.units() {
+            if let Unit::Box(b) = unit {
+                for i in cells.iter() {
+                    region_map[*i] = (b'A' + *b as u8) as char;
+                }
+            }
+        }
+        let region_map = region_map.iter().collect::<String>();
+        match Layout::jigsaw(&region_map) {
+            Ok(jigsaw) if jigsaw == *layout => Ok(LayoutDescription::Jigsaw(region_map)),
+            _ => Err(String::from("The layout of the puzzle can't be written down")),
+        }
+    }
+}
+
+/// Add a copy of every constraint to the sudoku
+fn add_all<C: Constraint + Clone + 'static>(sudoku: &mut Sudoku, constraints: &[C]) {
+    for constraint in constraints {
+        sudoku.add_constraint(Arc::new(constraint.clone()));
+    }
+}
+
+impl Description {
+    /// A description without any variant rules
+    pub fn new(givens: String) -> Self {
+        Description { givens, ..Description::default() }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid puzzle description: {}", e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a description always converts to JSON")
+    }
+
+    /// Describe the puzzle of the sudoku: its givens, layout and variant rules
+    /// Returns: an error if the layout or one of the rules can't be written down
+    pub fn from_sudoku(sudoku: &Sudoku) -> Result<Self, String> {
+        let mut res = Description::new(sudoku.givens_line());
+        res.layout = LayoutDescription::from_layout(&sudoku.layout)?;
+        for constraint in sudoku.constraints.iter() {
+            constraint.describe(&mut res)?;
+        }
+        Ok(res)
+    }
+
+    /// Create the sudoku, with every variant rule added as a constraint.
+    /// Cages come with the 45 rule, like in `KillerSudoku`.
+    pub fn to_sudoku(&self) -> Result<Sudoku, String> {
+        let mut sudoku = Sudoku::with_layout(self.givens.clone(), self.layout.to_layout()?)?;
+        if !self.cages.is_empty() {
+            check_cages(&self.cages)?;
+            add_all(&mut sudoku, &self.cages);
+            let rule = FortyFiveRule::new(&self.cages, &sudoku.layout)?;
+            sudoku.add_constraint(Arc::new(rule));
+        }
+        for chess_move in self.anti_chess.iter() {
+            sudoku.add_constraint(Arc::new(AntiMove::new(*chess_move)));
+        }
+        add_all(&mut sudoku, &self.thermometers);
+        add_all(&mut sudoku, &self.arrows);
+        add_all(&mut sudoku, &self.whispers);
+        add_all(&mut sudoku, &self.renban);
+        add_all(&mut sudoku, &self.palindromes);
+        add_all(&mut sudoku, &self.kropki);
+        add_all(&mut sudoku, &self.parity);
+        add_all(&mut sudoku, &self.greater_than);
+        add_all(&mut sudoku, &self.xv);
+        add_all(&mut sudoku, &self.sandwiches);
+        add_all(&mut sudoku, &self.little_killers);
+        Ok(sudoku)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::sudokutwo::constraint::AllDifferent;
+    use crate::sudokutwo::description::{Description, LayoutDescription};
+    use crate::sudokutwo::layout::{array_of, Unit};
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::dots::{Dot, GreaterThan, Kropki, Letter, Parity, ParityCell, Xv};
+    use crate::sudokutwo::lines::{Arrow, Thermometer};
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const SOLUTION: &str = "417369825632158947958724316825437169791586432346912758289643571573291684164875293";
+
+    /// `SOLUTION` without r1c2, r1c4, r2c2 and r2c4: a 1 and 3 that can swap places, so it has two solutions
+    fn rectangle() -> String {
+        SOLUTION.char_indices()
+            .map(|(i, c)| if [1, 3, 10, 12].contains(&i) { '0' } else { c })
+            .collect()
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut description = Description::new("0".repeat(81));
+        description.thermometers.push(Thermometer::new(vec!(0, 1, 2)).unwrap());
+        description.arrows.push(Arrow::new(40, vec!(30, 20)).unwrap());
+        description.kropki.push(Kropki::new(Dot::Black, 79, 80).unwrap());
+        let json = description.to_json();
+        assert!(json.contains(r#""thermometers":[["r1c1","r1c2","r1c3"]]"#));
+        assert!(json.contains(r#""kropki":[{"black":["r9c8","r9c9"]}]"#));
+        assert_eq!(Description::from_json(&json), Ok(description));
+    }
+
+    #[test]
+    fn invalid_json() {
+        assert!(Description::from_json(r#"{"givens": "0", "thermometers": [["r1c1"]]}"#).is_err());
+        assert!(Description::from_json(r#"{"givens": "0", "kropki": [{"white": ["r1c1", "r3c3"]}]}"#).is_err());
+        assert!(Description::from_json(r#"{"givens": "0", "snakes": []}"#).is_err());
+    }
+
+    #[test]
+    fn thermometer_makes_unique() {
+        let description = Description::new(rectangle());
+        assert_eq!(description.to_sudoku().unwrap().count_solutions(10), 2);
+        let json = format!(r#"{{"givens": "{}", "thermometers": [["r1c2", "r2c2"]]}}"#, rectangle());
+        let sudoku = Description::from_json(&json).unwrap().to_sudoku().unwrap();
+        assert_eq!(sudoku.count_solutions(10), 1);
+    }
+
+    #[test]
+    fn arrow_and_kropki_make_unique() {
+        // r1c2 holds 1 or 3: only 3 adds up to r1c3 (7) with r1c1 (4), and only 3 is consecutive to r1c1
+        let arrow = format!(r#"{{"givens": "{}", "arrows": [["r1c3", "r1c2", "r1c1"]]}}"#, rectangle());
+        let kropki = format!(r#"{{"givens": "{}", "kropki": [{{"white": ["r1c1", "r1c2"]}}]}}"#, rectangle());
+        for json in [arrow, kropki].iter() {
+            let mut sudoku = Description::from_json(json).unwrap().to_sudoku().unwrap();
+            assert_eq!(sudoku.count_solutions(10), 1);
+            assert!(sudoku.attempt_solve());
+            assert_eq!(&sudoku.to_line()[..4], "4371");
+        }
+    }
+
+    #[test]
+    fn local_markers_make_unique() {
+        // r1c2 and r2c2 hold 1 and 3 in either order: both are odd, so a parity marker can't tell them apart,
+        // but r2c2 > r1c2 or a V with r1c1 (4) only allow the first order
+        let mut odd = Description::new(rectangle());
+        odd.parity.push(ParityCell::new(Parity::Odd, 10).unwrap());
+        assert_eq!(odd.to_sudoku().unwrap().count_solutions(10), 2);
+
+        let mut greater = Description::new(rectangle());
+        greater.greater_than.push(GreaterThan::new(10, 1).unwrap());
+        let mut xv = Description::new(rectangle());
+        xv.xv.push(Xv::new(Letter::V, 1, 0).unwrap());
+        for description in [greater, xv].iter() {
+            let json = description.to_json();
+            assert_eq!(Description::from_json(&json).as_ref(), Ok(description));
+            assert_eq!(description.to_sudoku().unwrap().count_solutions(10), 1);
+        }
+    }
+
+    #[test]
+    fn outside_clues_make_unique() {
+        // row 1 reads 4 1 7 3 6 9 or 4 3 7 1 6 9: 16 or 6 between the 1 and the 9,
+        // and the diagonal from r1c2 adds up to 37 or 39
+        for (clues, expected) in [(r#""sandwiches": ["16=r1"]"#, "4173"), (r#""sandwiches": ["6=r1"]"#, "4371"),
+                                  (r#""little_killers": ["37=r1c2 se"]"#, "4173")].iter() {
+            let json = format!(r#"{{"givens": "{}", {}}}"#, rectangle(), clues);
+            let mut sudoku = Description::from_json(&json).unwrap().to_sudoku().unwrap();
+            assert_eq!(sudoku.count_solutions(10), 1);
+            assert!(sudoku.attempt_solve());
+            assert_eq!(&sudoku.to_line()[..4], *expected);
+        }
+        let json = format!(r#"{{"givens": "{}", "sandwiches": ["7=r1"]}}"#, rectangle());
+        assert_eq!(Description::from_json(&json).unwrap().to_sudoku().unwrap().count_solutions(10), 0);
+    }
+
+    #[test]
+    fn lines_make_unique() {
+        // r1c2 holds 1 or 3: only 1 is 5 away from r1c3 (7), only 3 makes a run with r1c1 (4),
+        // and only 1 reads the same as r3c8 (1)
+        for (lines, expected) in [(r#""whispers": [["r1c2", "r1c3"]]"#, "4173"),
+                                  (r#""renban": [["r1c1", "r1c2"]]"#, "4371"),
+                                  (r#""palindromes": [["r1c2", "r2c3", "r3c8"]]"#, "4173")].iter() {
+            let json = format!(r#"{{"givens": "{}", {}}}"#, rectangle(), lines);
+            let description = Description::from_json(&json).unwrap();
+            assert_eq!(Description::from_json(&description.to_json()).as_ref(), Ok(&description));
+            let mut sudoku = description.to_sudoku().unwrap();
+            assert_eq!(sudoku.count_solutions(10), 1);
+            assert!(sudoku.attempt_solve());
+            assert_eq!(&sudoku.to_line()[..4], *expected);
+        }
+    }
+
+    #[test]
+    fn describe_sudokus() {
+        let regions = concat!(
+            "AAABBCCCC", "ADABBBBCC", "ADDBBBCCF", "ADDEEEFCF", "AADEEFFFF",
+            "DDDHEEEFF", "GGGHEHIII", "GGGHHHHII", "GGGHHIIII",
+        );
+        let jigsaw = Sudoku::with_regions("0".repeat(81), regions).unwrap();
+        let description = Description::from_sudoku(&jigsaw).unwrap();
+        // the regions are lettered in the order they appear
+        let map = "AAABBCCCCADABBBBCCADDBBBCCEADDFFFECEAADFFEEEEDDDGFFFEEHHHGFGIIIHHHGGGGIIHHHGGIIII";
+        assert_eq!(description.layout, LayoutDescription::Jigsaw(String::from(map)));
+        assert_eq!(description.to_sudoku().unwrap().layout(), jigsaw.layout());
+        assert!(description.to_json().contains(&format!(r#""layout":{{"jigsaw":"{}"}}"#, map)));
+
+        let mut thermo = Description::new(rectangle());
+        thermo.thermometers.push(Thermometer::new(vec!(1, 10)).unwrap());
+        assert_eq!(Description::from_sudoku(&thermo.to_sudoku().unwrap()), Ok(thermo));
+
+        let mut other = Sudoku::new(rectangle()).unwrap();
+        other.add_constraint(Arc::new(AllDifferent::new(Unit::Diagonal(0), array_of(|i| i * 10))));
+        assert!(Description::from_sudoku(&other).is_err());
+    }
+}
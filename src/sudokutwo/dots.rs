@@ -0,0 +1,377 @@
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::constraint::{cell_name, Constraint, cross_out, parse_cell, parse_cells, prune_pair};
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{EntryNum, EVEN, ODD};
+
+/// Check that two cells exist and share an edge
+fn check_adjacent(a: usize, b: usize) -> Result<(), String> {
+    if a >= 81 || b >= 81 {
+        return Err(format!("Cells {} and {} don't both exist", a, b));
+    }
+    let (low, high) = (a.min(b), a.max(b));
+    if (high - low == 1 && low / 9 == high / 9) || high - low == 9 {
+        Ok(())
+    } else {
+        Err(format!("{} and {} don't share an edge", cell_name(a), cell_name(b)))
+    }
+}
+
+/// The colour of a kropki dot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dot {
+    /// The numbers are consecutive
+    White,
+    /// One number is double the other
+    Black,
+}
+
+impl Dot {
+    pub fn allows(self, a: u16, b: u16) -> bool {
+        match self {
+            Dot::White => a + 1 == b || b + 1 == a,
+            Dot::Black => a * 2 == b || b * 2 == a,
+        }
+    }
+}
+
+/// A dot on the edge between two cells
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "KropkiJson", into = "KropkiJson")]
+pub struct Kropki {
+    dot: Dot,
+    cells: (usize, usize),
+}
+
+impl Kropki {
+    pub fn new(dot: Dot, a: usize, b: usize) -> Result<Self, String> {
+        check_adjacent(a, b)?;
+        Ok(Kropki { dot, cells: (a, b) })
+    }
+
+    pub fn dot(&self) -> Dot {
+        self.dot
+    }
+
+    pub fn cells(&self) -> (usize, usize) {
+        self.cells
+    }
+}
+
+impl Constraint for Kropki {
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let dot = self.dot;
+        prune_pair(data, self.cells.0, self.cells.1, |a, b| dot.allows(a, b))
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.kropki.push(self.clone());
+        Ok(())
+    }
+}
+
+/// A kropki dot in a description: `{"white": ["r1c1", "r1c2"]}` or `{"black": [...]}`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum KropkiJson {
+    White([String; 2]),
+    Black([String; 2]),
+}
+
+impl TryFrom<KropkiJson> for Kropki {
+    type Error = String;
+
+    fn try_from(json: KropkiJson) -> Result<Self, Self::Error> {
+        let (dot, names) = match json {
+            KropkiJson::White(names) => (Dot::White, names),
+            KropkiJson::Black(names) => (Dot::Black, names),
+        };
+        let cells = parse_cells(&names)?;
+        Kropki::new(dot, cells[0], cells[1])
+    }
+}
+
+impl From<Kropki> for KropkiJson {
+    fn from(kropki: Kropki) -> Self {
+        let names = [cell_name(kropki.cells.0), cell_name(kropki.cells.1)];
+        match kropki.dot {
+            Dot::White => KropkiJson::White(names),
+            Dot::Black => KropkiJson::Black(names),
+        }
+    }
+}
+
+/// Whether a shaded cell holds an even or an odd number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parity {
+    Even,
+    Odd,
+}
+
+/// A cell that can only hold even (usually drawn as a square) or odd (a circle) numbers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "ParityJson", into = "ParityJson")]
+pub struct ParityCell {
+    parity: Parity,
+    cell: usize,
+}
+
+impl ParityCell {
+    pub fn new(parity: Parity, cell: usize) -> Result<Self, String> {
+        if cell >= 81 {
+            return Err(format!("Cell {} doesn't exist", cell));
+        }
+        Ok(ParityCell { parity, cell })
+    }
+
+    pub fn parity(&self) -> Parity {
+        self.parity
+    }
+
+    pub fn cell(&self) -> usize {
+        self.cell
+    }
+}
+
+impl Constraint for ParityCell {
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let to_remove = match self.parity {
+            Parity::Even => ODD,
+            Parity::Odd => EVEN,
+        };
+        cross_out(data, self.cell, to_remove)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.parity.push(self.clone());
+        Ok(())
+    }
+}
+
+/// A parity cell in a description: `{"even": "r1c1"}` or `{"odd": "r1c1"}`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ParityJson {
+    Even(String),
+    Odd(String),
+}
+
+impl TryFrom<ParityJson> for ParityCell {
+    type Error = String;
+
+    fn try_from(json: ParityJson) -> Result<Self, Self::Error> {
+        let (parity, name) = match json {
+            ParityJson::Even(name) => (Parity::Even, name),
+            ParityJson::Odd(name) => (Parity::Odd, name),
+        };
+        let cell = parse_cell(&name).ok_or_else(|| format!("'{}' is not a cell", name))?;
+        ParityCell::new(parity, cell)
+    }
+}
+
+impl From<ParityCell> for ParityJson {
+    fn from(parity_cell: ParityCell) -> Self {
+        let name = cell_name(parity_cell.cell);
+        match parity_cell.parity {
+            Parity::Even => ParityJson::Even(name),
+            Parity::Odd => ParityJson::Odd(name),
+        }
+    }
+}
+
+/// A greater-than sign between two cells, pointing from the greater cell at the smaller one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct GreaterThan {
+    greater: usize,
+    smaller: usize,
+}
+
+impl GreaterThan {
+    pub fn new(greater: usize, smaller: usize) -> Result<Self, String> {
+        check_adjacent(greater, smaller)?;
+        Ok(GreaterThan { greater, smaller })
+    }
+
+    pub fn greater(&self) -> usize {
+        self.greater
+    }
+
+    pub fn smaller(&self) -> usize {
+        self.smaller
+    }
+}
+
+impl Constraint for GreaterThan {
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        prune_pair(data, self.greater, self.smaller, |a, b| a > b)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.greater_than.push(self.clone());
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<String>> for GreaterThan {
+    type Error = String;
+
+    /// The greater cell first, then the smaller one
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        match parse_cells(&names)?[..] {
+            [greater, smaller] => GreaterThan::new(greater, smaller),
+            _ => Err(format!("A greater-than sign needs two cells, found {}", names.len())),
+        }
+    }
+}
+
+impl From<GreaterThan> for Vec<String> {
+    fn from(sign: GreaterThan) -> Self {
+        vec!(cell_name(sign.greater), cell_name(sign.smaller))
+    }
+}
+
+/// The letter on the edge between two cells in an XV sudoku
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Letter {
+    /// The numbers add up to 10
+    X,
+    /// The numbers add up to 5
+    V,
+}
+
+impl Letter {
+    pub fn sum(self) -> u16 {
+        match self {
+            Letter::X => 10,
+            Letter::V => 5,
+        }
+    }
+}
+
+/// An X or a V on the edge between two cells
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "XvJson", into = "XvJson")]
+pub struct Xv {
+    letter: Letter,
+    cells: (usize, usize),
+}
+
+impl Xv {
+    pub fn new(letter: Letter, a: usize, b: usize) -> Result<Self, String> {
+        check_adjacent(a, b)?;
+        Ok(Xv { letter, cells: (a, b) })
+    }
+
+    pub fn letter(&self) -> Letter {
+        self.letter
+    }
+
+    pub fn cells(&self) -> (usize, usize) {
+        self.cells
+    }
+}
+
+impl Constraint for Xv {
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let sum = self.letter.sum();
+        prune_pair(data, self.cells.0, self.cells.1, |a, b| a + b == sum)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.xv.push(self.clone());
+        Ok(())
+    }
+}
+
+/// An X or V in a description: `{"x": ["r1c1", "r1c2"]}` or `{"v": [...]}`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum XvJson {
+    X([String; 2]),
+    V([String; 2]),
+}
+
+impl TryFrom<XvJson> for Xv {
+    type Error = String;
+
+    fn try_from(json: XvJson) -> Result<Self, Self::Error> {
+        let (letter, names) = match json {
+            XvJson::X(names) => (Letter::X, names),
+            XvJson::V(names) => (Letter::V, names),
+        };
+        let cells = parse_cells(&names)?;
+        Xv::new(letter, cells[0], cells[1])
+    }
+}
+
+impl From<Xv> for XvJson {
+    fn from(xv: Xv) -> Self {
+        let names = [cell_name(xv.cells.0), cell_name(xv.cells.1)];
+        match xv.letter {
+            Letter::X => XvJson::X(names),
+            Letter::V => XvJson::V(names),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::constraint::Constraint;
+    use crate::sudokutwo::dots::{Dot, GreaterThan, Kropki, Letter, Parity, ParityCell, Xv};
+    use crate::sudokutwo::entry_num::{ALL, EntryNumThings, FIVE, NINE, ONE, THREE};
+
+    #[test]
+    fn white_dot() {
+        let mut data = [ALL; 81];
+        data[0] = FIVE;
+        assert!(Kropki::new(Dot::White, 0, 1).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[1].get_pos(), vec!(4, 6));
+    }
+
+    #[test]
+    fn black_dot() {
+        let mut data = [ALL; 81];
+        assert!(Kropki::new(Dot::Black, 0, 9).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[0].get_pos(), vec!(1, 2, 3, 4, 6, 8));
+        data[9] = NINE;
+        assert!(Kropki::new(Dot::Black, 0, 9).unwrap().check(&data).is_err());
+    }
+
+    #[test]
+    fn dots_share_an_edge() {
+        assert!(Kropki::new(Dot::White, 8, 9).is_err());
+        assert!(Kropki::new(Dot::White, 0, 10).is_err());
+        assert!(Kropki::new(Dot::White, 10, 1).is_ok());
+    }
+
+    #[test]
+    fn parity() {
+        let mut data = [ALL; 81];
+        assert_eq!(ParityCell::new(Parity::Even, 0).unwrap().prune(&mut data), Ok(5));
+        assert_eq!(data[0].get_pos(), vec!(2, 4, 6, 8));
+        data[1] = ONE | THREE;
+        assert!(ParityCell::new(Parity::Even, 1).unwrap().check(&data).is_err());
+    }
+
+    #[test]
+    fn greater_than() {
+        let mut data = [ALL; 81];
+        data[1] = THREE;
+        assert!(GreaterThan::new(0, 1).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[0].get_pos(), vec!(4, 5, 6, 7, 8, 9));
+        assert!(GreaterThan::new(1, 0).unwrap().check(&data).is_err());
+    }
+
+    #[test]
+    fn x_and_v() {
+        let mut data = [ALL; 81];
+        assert!(Xv::new(Letter::V, 0, 1).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[0].get_pos(), vec!(1, 2, 3, 4));
+        data[9] = FIVE;
+        assert!(Xv::new(Letter::X, 9, 18).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[18].get_pos(), vec!(5));
+    }
+}
@@ -12,6 +12,8 @@ pub const SEVEN: EntryNum = 0b1000000;
 pub const EIGHT: EntryNum = 0b10000000;
 pub const NINE: EntryNum = 0b100000000;
 pub const ALL: EntryNum = 0b111111111;
+pub const ODD: EntryNum = ONE | THREE | FIVE | SEVEN | NINE;
+pub const EVEN: EntryNum = TWO | FOUR | SIX | EIGHT;
 
 //
 ///// check that the num is fixed
@@ -30,21 +32,42 @@ pub fn to_entry_num(num: u16) -> EntryNum {
     NUMS[(num - 1) as usize]
 }
 
+/// All the numbers from `low` up to and including `high`, combined in a single entry num
+/// ```rust
+/// use sudoku_solver::sudokutwo::entry_num::{range, EntryNumThings};
+/// assert_eq!(range(3, 5).get_pos(), vec!(3, 4, 5));
+/// assert_eq!(range(5, 3), 0);
+/// ```
+pub fn range(low: u16, high: u16) -> EntryNum {
+    let (low, high) = (low.max(1), high.min(9));
+    if low > high {
+        return 0;
+    }
+    ((1 << high) - 1) & !((1 << (low - 1)) - 1)
+}
+
 pub trait EntryNumThings {
-    fn is_fixed(self) -> bool;
+    fn is_fixed(&self) -> bool;
     fn get_fixed(self) -> EntryNum;
 
     fn get_entry_num(num: u16) -> Option<EntryNum>;
 
     /// Get all the possible numbers
     /// ```rust
-    /// assert_eq!(get_pos(8), vec!(8))
+    /// use sudoku_solver::sudokutwo::entry_num::{EIGHT, EntryNumThings};
+    /// assert_eq!(EIGHT.get_pos(), vec!(8))
     /// ```
     fn get_pos(self) -> Vec<u16>;
+
+    /// The smallest possible number, if any
+    fn lowest(self) -> Option<u16>;
+
+    /// The largest possible number, if any
+    fn highest(self) -> Option<u16>;
 }
 
 impl EntryNumThings for EntryNum {
-    fn is_fixed(self) -> bool {
+    fn is_fixed(&self) -> bool {
         self.count_ones() == 1
     }
 
@@ -73,6 +96,20 @@ impl EntryNumThings for EntryNum {
         }
         res
     }
+
+    fn lowest(self) -> Option<u16> {
+        match self & ALL {
+            0 => None,
+            en => Some(en.trailing_zeros() as u16 + 1),
+        }
+    }
+
+    fn highest(self) -> Option<u16> {
+        match self & ALL {
+            0 => None,
+            en => Some(16 - en.leading_zeros() as u16),
+        }
+    }
 }
 //
 //pub fn get_fixed_num(num: EntryNum) -> u16 {
@@ -145,4 +182,13 @@ mod tests {
         let possible: EntryNum = (possible ^ to_remove) & possible;
         assert_eq!(possible.get_pos(), vec!(1, 3));
     }
+
+    #[test]
+    fn bounds() {
+        assert_eq!((TWO | FIVE | SEVEN).lowest(), Some(2));
+        assert_eq!((TWO | FIVE | SEVEN).highest(), Some(7));
+        assert_eq!(0.lowest(), None);
+        assert_eq!(range(1, 9), ALL);
+        assert_eq!(range(0, 1), ONE);
+    }
 }
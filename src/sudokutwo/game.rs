@@ -0,0 +1,473 @@
+use std::convert::TryFrom;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::constraint::{cell_name, parse_cell};
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{self, EntryNum};
+use crate::sudokutwo::origin::Origin;
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+/// Something the player did to a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Action {
+    /// Fill in a number
+    Place { cell: usize, num: u16 },
+    /// Erase the number and the candidates
+    Remove { cell: usize },
+    /// Pencil a candidate in, or out again
+    ToggleCandidate { cell: usize, num: u16 },
+    /// Let the solver fill in the cell
+    Hint { cell: usize },
+}
+
+impl Action {
+    pub fn cell(&self) -> usize {
+        match *self {
+            Action::Place { cell, .. } | Action::Remove { cell } |
+            Action::ToggleCandidate { cell, .. } | Action::Hint { cell } => cell,
+        }
+    }
+}
+
+impl Display for Action {
+    /// Written like the keys of the play mode: `5=r1c3`, `x=r1c3`, `p5=r1c3` or `?=r1c3`
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match *self {
+            Action::Place { cell, num } => write!(f, "{}={}", num, cell_name(cell)),
+            Action::Remove { cell } => write!(f, "x={}", cell_name(cell)),
+            Action::ToggleCandidate { cell, num } => write!(f, "p{}={}", num, cell_name(cell)),
+            Action::Hint { cell } => write!(f, "?={}", cell_name(cell)),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not an action", s);
+        let (what, cell) = s.split_once('=').ok_or_else(invalid)?;
+        let cell = parse_cell(cell.trim()).ok_or_else(invalid)?;
+        let num = |n: &str| n.parse::<u16>().ok().filter(|n| (1..=9).contains(n)).ok_or_else(invalid);
+        match what.trim() {
+            "x" => Ok(Action::Remove { cell }),
+            "?" => Ok(Action::Hint { cell }),
+            w if w.starts_with('p') => Ok(Action::ToggleCandidate { cell, num: num(&w[1..])? }),
+            w => Ok(Action::Place { cell, num: num(w)? }),
+        }
+    }
+}
+
+impl TryFrom<String> for Action {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Action> for String {
+    fn from(action: Action) -> Self {
+        action.to_string()
+    }
+}
+
+/// The board as the player sees it: the sudoku with their numbers, and the candidates they pencilled in
+#[derive(Clone)]
+struct Board {
+    sudoku: Sudoku,
+    candidates: [EntryNum; 81],
+}
+
+impl Board {
+    fn apply(&mut self, action: Action, solution: Option<&Sudoku>) -> Result<(), String> {
+        let cell = action.cell();
+        match action {
+            Action::Place { num, .. } => {
+                self.sudoku.place(cell, num)?;
+                self.candidates[cell] = 0;
+            }
+            Action::Remove { .. } => {
+                if self.sudoku.origin(cell).is_none() && self.candidates[cell] == 0 {
+                    return Err(format!("{} is empty already", cell_name(cell)));
+                }
+                self.sudoku.clear(cell)?;
+                self.candidates[cell] = 0;
+            }
+            Action::ToggleCandidate { num, .. } => {
+                if self.sudoku.origin(cell).is_some() {
+                    return Err(format!("{} is filled in already", cell_name(cell)));
+                }
+                self.candidates[cell] ^= entry_num::to_entry_num(num);
+            }
+            Action::Hint { .. } => {
+                let num = solution.ok_or_else(|| String::from("The puzzle has no unique solution"))?.data[cell];
+                if self.sudoku.data[cell] == num {
+                    return Err(format!("{} is right already", cell_name(cell)));
+                }
+                self.sudoku.fill(cell, num, Origin::Logic);
+                self.candidates[cell] = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A puzzle being played: every action of the player is kept, so any of them can be undone and redone.
+/// A state is rebuilt by replaying the actions on the givens.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "GameJson", into = "GameJson")]
+pub struct Game {
+    /// The puzzle as it is saved, with its layout and variant rules
+    description: Description,
+    /// The puzzle with only its givens filled in
+    puzzle: Sudoku,
+    solution: Option<Sudoku>,
+    board: Board,
+    /// Every action, including the undone ones at the end
+    history: Vec<Action>,
+    /// How many actions of the history are applied
+    position: usize,
+    mistakes: u32,
+    /// The time played before the clock last started
+    elapsed: Duration,
+    /// When the clock started, None while paused
+    started: Option<Instant>,
+}
+
+impl Game {
+    /// Start a game with the givens, layout and variant rules of the puzzle, with the clock running
+    /// Returns: an error if the puzzle can't be written down with `Description::from_sudoku`, as it couldn't be saved
+    pub fn new(puzzle: &Sudoku) -> Result<Self, String> {
+        let description = Description::from_sudoku(puzzle)?;
+        let puzzle = puzzle.with_givens(&puzzle.givens());
+        // hints and mistakes go by the solution, so it has to be the only one
+        let mut solution = puzzle.clone();
+        let solution = if puzzle.check_unique().is_ok() && solution.attempt_solve() { Some(solution) } else { None };
+        Ok(Game {
+            description,
+            board: Board { sudoku: puzzle.clone(), candidates: [0; 81] },
+            puzzle,
+            solution,
+            history: vec!(),
+            position: 0,
+            mistakes: 0,
+            elapsed: Duration::from_secs(0),
+            started: Some(Instant::now()),
+        })
+    }
+
+    /// The sudoku with the numbers filled in so far
+    pub fn sudoku(&self) -> &Sudoku {
+        &self.board.sudoku
+    }
+
+    /// The solution, None if the puzzle doesn't have exactly one
+    pub fn solution(&self) -> Option<&Sudoku> {
+        self.solution.as_ref()
+    }
+
+    /// The candidates the player pencilled in
+    pub fn candidates(&self, cell: usize) -> EntryNum {
+        self.board.candidates[cell]
+    }
+
+    /// The actions that are applied, oldest first
+    pub fn history(&self) -> &[Action] {
+        &self.history[..self.position]
+    }
+
+    /// How many numbers were placed that don't match the solution, undone or not.
+    /// Without a unique solution, a number is a mistake when it leaves the board without any solution.
+    pub fn mistakes(&self) -> u32 {
+        self.mistakes
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed + self.started.map_or(Duration::from_secs(0), |started| started.elapsed())
+    }
+
+    pub fn pause(&mut self) {
+        self.elapsed = self.elapsed();
+        self.started = None;
+    }
+
+    pub fn resume(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.started.is_none()
+    }
+
+    /// Returns true iff. every cell is filled in and nothing conflicts
+    pub fn is_solved(&self) -> bool {
+        self.board.sudoku.is_solved()
+    }
+
+    /// Carry out an action, which forgets the actions that were undone
+    /// Returns: an error if the action is not possible, leaving the game as it was
+    pub fn play(&mut self, action: Action) -> Result<(), String> {
+        if action.cell() >= 81 {
+            return Err(format!("Cell {} doesn't exist", action.cell()));
+        }
+        self.board.apply(action, self.solution.as_ref())?;
+        self.history.truncate(self.position);
+        self.history.push(action);
+        self.position += 1;
+        if let Action::Place { cell, num } = action {
+            let mistake = match self.solution.as_ref() {
+                Some(solution) => solution.data[cell] != entry_num::to_entry_num(num),
+                None => self.board.sudoku.is_valid().is_err() || self.board.sudoku.count_solutions(1) == 0,
+            };
+            if mistake {
+                self.mistakes += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns: false if there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        self.position -= 1;
+        self.replay().expect("actions that were played can be played again");
+        true
+    }
+
+    /// Returns: false if there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        if self.position == self.history.len() {
+            return false;
+        }
+        self.board.apply(self.history[self.position], self.solution.as_ref())
+            .expect("actions that were played can be played again");
+        self.position += 1;
+        true
+    }
+
+    /// Rebuild the board from the givens and the applied actions
+    fn replay(&mut self) -> Result<(), String> {
+        let mut board = Board { sudoku: self.puzzle.clone(), candidates: [0; 81] };
+        for action in self.history[..self.position].iter() {
+            board.apply(*action, self.solution.as_ref())
+                .map_err(|e| format!("Can't replay {}: {}", action, e))?;
+        }
+        self.board = board;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a game always converts to JSON")
+    }
+
+    /// Resume a saved game, with the clock running
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid game: {}", e))
+    }
+}
+
+/// A saved game: the puzzle, every action with how many of the last ones are undone, and the counters
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GameJson {
+    puzzle: Description,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    actions: Vec<Action>,
+    #[serde(default)]
+    undone: usize,
+    #[serde(default)]
+    mistakes: u32,
+    #[serde(default)]
+    elapsed_ms: u64,
+}
+
+impl TryFrom<GameJson> for Game {
+    type Error = String;
+
+    fn try_from(json: GameJson) -> Result<Self, Self::Error> {
+        let mut game = Game::new(&json.puzzle.to_sudoku()?)?;
+        if json.undone > json.actions.len() {
+            return Err(format!("Can't undo {} of {} actions", json.undone, json.actions.len()));
+        }
+        game.position = json.actions.len() - json.undone;
+        game.history = json.actions;
+        game.replay()?;
+        game.mistakes = json.mistakes;
+        game.elapsed = Duration::from_millis(json.elapsed_ms);
+        Ok(game)
+    }
+}
+
+impl From<Game> for GameJson {
+    fn from(game: Game) -> Self {
+        GameJson {
+            undone: game.history.len() - game.position,
+            elapsed_ms: game.elapsed().as_millis() as u64,
+            puzzle: game.description,
+            actions: game.history,
+            mistakes: game.mistakes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::sudokutwo::constraint::AllDifferent;
+    use crate::sudokutwo::entry_num::{EIGHT, EntryNumThings, FOUR, ONE, THREE};
+    use crate::sudokutwo::game::{Action, Game};
+    use crate::sudokutwo::killer::KillerSudoku;
+    use crate::sudokutwo::layout::{array_of, Unit};
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+
+    fn game() -> Game {
+        Game::new(&Sudoku::new(String::from(EASY)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parse_actions() {
+        for (text, action) in [("5=r1c3", Action::Place { cell: 2, num: 5 }), ("x=r9c9", Action::Remove { cell: 80 }),
+                               ("p1=r2c1", Action::ToggleCandidate { cell: 9, num: 1 }), ("?=r1c1", Action::Hint { cell: 0 })].iter() {
+            assert_eq!(text.parse(), Ok(*action));
+            assert_eq!(action.to_string(), *text);
+        }
+        assert!("0=r1c1".parse::<Action>().is_err());
+        assert!("p=r1c1".parse::<Action>().is_err());
+        assert!("5=r0c1".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut game = game();
+        assert!(game.play(Action::Place { cell: 2, num: 4 }).is_err());
+        game.play(Action::ToggleCandidate { cell: 0, num: 1 }).unwrap();
+        game.play(Action::ToggleCandidate { cell: 0, num: 3 }).unwrap();
+        game.play(Action::Place { cell: 0, num: 4 }).unwrap();
+        assert_eq!(game.candidates(0), 0);
+        assert!(game.undo());
+        assert_eq!(game.sudoku().origin(0), None);
+        assert_eq!(game.candidates(0), ONE | THREE);
+        assert!(game.undo() && game.undo());
+        assert!(!game.undo());
+        assert!(game.redo() && game.redo() && game.redo());
+        assert!(!game.redo());
+        assert_eq!(game.sudoku().data[0].get_fixed(), 4);
+        assert!(game.undo());
+        game.play(Action::Remove { cell: 0 }).unwrap();
+        assert!(!game.redo());
+        assert_eq!(game.history().len(), 3);
+    }
+
+    #[test]
+    fn count_mistakes() {
+        let mut game = game();
+        game.play(Action::Place { cell: 0, num: 1 }).unwrap();
+        game.play(Action::Place { cell: 0, num: 4 }).unwrap();
+        assert!(game.undo() && game.undo());
+        assert!(game.redo() && game.redo());
+        assert_eq!(game.mistakes(), 1);
+        assert!(!game.is_solved());
+        for cell in 0..81 {
+            let _ = game.play(Action::Hint { cell });
+        }
+        assert!(game.is_solved());
+        assert_eq!(game.mistakes(), 1);
+    }
+
+    #[test]
+    fn no_unique_solution() {
+        // only the first row is given, so the rest can be filled in many ways
+        let mut game = Game::new(&Sudoku::new(format!("123456789{}", "0".repeat(72))).unwrap()).unwrap();
+        assert!(game.solution().is_none());
+        assert_eq!(game.play(Action::Hint { cell: 9 }), Err(String::from("The puzzle has no unique solution")));
+        game.play(Action::Place { cell: 9, num: 4 }).unwrap();
+        assert_eq!(game.mistakes(), 0);
+        // a second 4 in the row leaves no solution
+        game.play(Action::Place { cell: 10, num: 4 }).unwrap();
+        assert_eq!(game.mistakes(), 1);
+        assert!(game.undo());
+        // a 1 under the given 1 is one too
+        game.play(Action::Place { cell: 18, num: 1 }).unwrap();
+        assert_eq!(game.mistakes(), 2);
+        game.play(Action::Place { cell: 18, num: 5 }).unwrap();
+        assert_eq!(game.mistakes(), 2);
+    }
+
+    #[test]
+    fn clock() {
+        let mut game = game();
+        game.pause();
+        let paused = game.elapsed();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(game.elapsed(), paused);
+        game.resume();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(game.elapsed() >= paused + Duration::from_millis(5));
+    }
+
+    #[test]
+    fn save_and_resume() {
+        let mut game = game();
+        game.play(Action::ToggleCandidate { cell: 0, num: 1 }).unwrap();
+        game.play(Action::Place { cell: 1, num: 1 }).unwrap();
+        game.play(Action::Hint { cell: 1 }).unwrap();
+        game.undo();
+        game.pause();
+        let json = game.to_json();
+        assert!(json.contains(r#""actions":["p1=r1c1","1=r1c2","?=r1c2"],"undone":1,"mistakes":1"#), "{}", json);
+        let mut resumed = Game::from_json(&json).unwrap();
+        assert_eq!(resumed.history(), game.history());
+        assert_eq!(resumed.sudoku().to_line(), game.sudoku().to_line());
+        assert_eq!(resumed.candidates(0), ONE);
+        assert!(resumed.elapsed() + Duration::from_millis(1) >= game.elapsed());
+        assert!(resumed.redo());
+        assert_eq!(resumed.sudoku().data[1].get_fixed(), 8);
+
+        assert!(Game::from_json(&format!(r#"{{"puzzle": {{"givens": "{}"}}, "actions": ["4=r1c3"]}}"#, EASY)).is_err());
+        assert!(Game::from_json(&format!(r#"{{"puzzle": {{"givens": "{}"}}, "undone": 1}}"#, EASY)).is_err());
+    }
+
+    #[test]
+    fn killer_game() {
+        let killer = KillerSudoku::parse(&format!("{}\n12=r1c1 r1c2", EASY)).unwrap();
+        let mut game = Game::new(killer.sudoku()).unwrap();
+        let solution = game.solution().unwrap();
+        assert_eq!(solution.data[0] | solution.data[1], FOUR | EIGHT);
+        game.play(Action::Hint { cell: 0 }).unwrap();
+        let json = game.to_json();
+        assert!(json.contains(r#""puzzle":{"givens":"#) && json.contains(r#""cages":["12=r1c1 r1c2"]"#), "{}", json);
+        let resumed = Game::from_json(&json).unwrap();
+        assert_eq!(resumed.sudoku().to_line(), game.sudoku().to_line());
+        assert_eq!(resumed.solution().unwrap().to_line(), game.solution().unwrap().to_line());
+    }
+
+    #[test]
+    fn start_from_the_givens() {
+        let mut s = Sudoku::new(String::from(EASY)).unwrap();
+        s.place(0, 1).unwrap();
+        assert!(s.solve_once() > 0);
+        let game = Game::new(&s).unwrap();
+        assert_eq!(game.sudoku().to_line(), EASY);
+        assert_eq!(game.solution().unwrap().data[0].get_fixed(), 4);
+
+        let mut other = Sudoku::new(String::from(EASY)).unwrap();
+        other.add_constraint(Arc::new(AllDifferent::new(Unit::Diagonal(0), array_of(|i| i * 10))));
+        assert!(Game::new(&other).is_err());
+    }
+}
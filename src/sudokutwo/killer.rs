@@ -0,0 +1,424 @@
+use std::convert::TryFrom;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::constraint::{cell_name, Constraint, parse_cell};
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{ALL, EntryNum, EntryNumThings};
+use crate::sudokutwo::sudoku_api::SudokuApi;
+use crate::sudokutwo::layout::Layout;
+
+/// A group of cells whose numbers add up to `sum`, without repeating a number
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cage {
+    pub sum: u16,
+    pub cells: Vec<usize>,
+}
+
+impl FromStr for Cage {
+    type Err = String;
+
+    /// Parse a cage written as `<sum>=<cell> <cell> ...`, with cells written as `r<row>c<col>` (1-based),
+    /// for example `15=r1c1 r1c2 r2c1`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sum, cells) = s.split_once('=')
+            .ok_or_else(|| format!("Cage '{}' needs to be written as <sum>=<cells>", s))?;
+        let sum = sum.trim().parse::<u16>()
+            .map_err(|_| format!("Cage '{}' has an invalid sum '{}'", s, sum.trim()))?;
+        let cells = cells.split_whitespace()
+            .map(|cell| parse_cell(cell).ok_or_else(|| format!("Cage '{}' has an invalid cell '{}'", s, cell)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Cage { sum, cells })
+    }
+}
+
+impl Display for Cage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let cells = self.cells.iter().map(|i| cell_name(*i)).collect::<Vec<_>>();
+        write!(f, "{}={}", self.sum, cells.join(" "))
+    }
+}
+
+impl TryFrom<String> for Cage {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Cage> for String {
+    fn from(cage: Cage) -> Self {
+        cage.to_string()
+    }
+}
+
+/// The sum of all the numbers in the entry num
+fn digit_sum(en: EntryNum) -> u16 {
+    en.get_pos().iter().sum()
+}
+
+/// All the sets of `size` different numbers that add up to `sum`
+pub(crate) fn cage_combinations(size: usize, sum: u16) -> impl Iterator<Item=EntryNum> {
+    (1..=ALL).filter(move |en| en.count_ones() as usize == size && digit_sum(*en) == sum)
+}
+
+/// Whether the cells can hold the numbers of the combination:
+/// every cell needs a number of the combination, and every number needs a cell
+pub(crate) fn combination_fits(data: &[EntryNum; 81], cells: &[usize], combination: EntryNum) -> bool {
+    let all_possible = cells.iter().fold(0, |cur, i| cur | data[*i]);
+    cells.iter().all(|i| data[*i] & combination > 0) && all_possible & combination == combination
+}
+
+/// Cross out every number that does not appear in any combination that fits the cage
+/// Returns: the number of possibilities crossed out
+fn prune_cage(data: &mut [EntryNum; 81], cells: &[usize], sum: u16) -> u32 {
+    let mut allowed: [EntryNum; 9] = [0; 9];
+    for combination in cage_combinations(cells.len(), sum) {
+        if !combination_fits(data, cells, combination) {
+            continue;
+        }
+        for (a, i) in allowed.iter_mut().zip(cells) {
+            *a |= data[*i] & combination;
+        }
+    }
+    cells.iter().zip(allowed.iter())
+        .map(|(i, a)| Sudoku::remove_possibilities(data, *i, ALL & !a))
+        .sum()
+}
+
+impl Cage {
+    /// Returns: an error if a cell of the cage is outside the grid
+    fn check_range(&self) -> Result<(), String> {
+        match self.cells.iter().find(|i| **i >= 81) {
+            Some(i) => Err(format!("Cage '{}' has cell {}, but there are only 81", self, i)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Constraint for Cage {
+    /// Cage sum pruning: every cell of a cage can only hold numbers from the combinations that add up to its sum
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        self.check_range()?;
+        let res = prune_cage(data, &self.cells, self.sum);
+        if self.cells.iter().any(|i| data[*i] == 0) {
+            return Err(format!("Cage '{}' can't add up to {} anymore", self, self.sum));
+        }
+        Ok(res)
+    }
+
+    /// No fixed number repeats inside the cage, a complete cage adds up to its sum,
+    /// and an incomplete one still can
+    fn check(&self, data: &[EntryNum; 81]) -> Result<(), String> {
+        self.check_range()?;
+        let fixed = self.cells.iter().map(|i| data[*i]).filter(|en| en.is_fixed()).collect::<Vec<_>>();
+        let combined = fixed.iter().fold(0, |cur, en| cur | en);
+        if combined.count_ones() as usize != fixed.len() {
+            return Err(format!("Cage '{}' repeats a number", self));
+        }
+        if fixed.len() == self.cells.len() && digit_sum(combined) != self.sum {
+            return Err(format!("Cage '{}' adds up to {}", self, digit_sum(combined)));
+        }
+        let mut copy = *data;
+        self.prune(&mut copy).map(|_| ())
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.cages.push(self.clone());
+        Ok(())
+    }
+}
+
+/// The "45 rule": every unit of the layout (row, column, block, jigsaw region, ...) adds up to 45.
+/// When the cages inside a unit cover all but a few of its cells, those innies add up to the difference.
+/// When the cages touching a unit cover all of it, the cells sticking out (outies) add up to the surplus.
+/// Both give extra cages that are pruned the same way as the real ones.
+/// Cages inside a unit that add up to more than 45, or cover all of it without adding up to 45, can't be solved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FortyFiveRule {
+    implied: Vec<Cage>,
+}
+
+impl FortyFiveRule {
+    /// The implied cages of the units of the layout
+    /// Returns: an error if the cages inside a unit can't add up to 45
+    pub fn new(cages: &[Cage], layout: &Layout) -> Result<Self, String> {
+        let mut implied = vec!();
+        for (name, unit) in layout.units() {
+            let inside = cages.iter().filter(|c| c.cells.iter().all(|i| unit.contains(i)));
+            let touching = cages.iter().filter(|c| c.cells.iter().any(|i| unit.contains(i)));
+            let inside_sum: u16 = inside.clone().map(|c| c.sum).sum();
+            let innies = unit.iter().copied()
+                .filter(|i| !inside.clone().any(|c| c.cells.contains(i)))
+                .collect::<Vec<_>>();
+            if inside_sum > 45 || (innies.is_empty() && inside_sum != 45) {
+                return Err(format!("The cages inside {} add up to {} instead of 45", name, inside_sum));
+            }
+            if !innies.is_empty() && innies.len() < 9 {
+                implied.push(Cage { sum: 45 - inside_sum, cells: innies });
+            }
+            let touching_sum: u16 = touching.clone().map(|c| c.sum).sum();
+            let covered = unit.iter().all(|i| touching.clone().any(|c| c.cells.contains(i)));
+            if covered && touching_sum < 45 {
+                return Err(format!("The cages covering {} add up to {}, less than 45", name, touching_sum));
+            }
+            let outies = touching.flat_map(|c| c.cells.iter().copied())
+                .filter(|i| !unit.contains(i))
+                .collect::<Vec<_>>();
+            // outies can only be pruned as a cage when they can't repeat a number
+            if covered && !outies.is_empty() && touching_sum >= 45 && in_one_unit(layout, &outies) {
+                implied.push(Cage { sum: touching_sum - 45, cells: outies });
+            }
+        }
+        implied.sort_by(|a, b| a.cells.cmp(&b.cells));
+        implied.dedup();
+        Ok(FortyFiveRule { implied })
+    }
+
+    /// The cages implied by the 45 rule
+    pub fn implied(&self) -> &[Cage] {
+        &self.implied
+    }
+}
+
+fn in_one_unit(layout: &Layout, cells: &[usize]) -> bool {
+    layout.cell_units(cells[0]).iter().any(|u| cells.iter().all(|i| layout.cell_units(*i).contains(u)))
+}
+
+impl Constraint for FortyFiveRule {
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let mut res = 0;
+        for cage in &self.implied {
+            res += cage.prune(data)?;
+        }
+        Ok(res)
+    }
+
+    /// Nothing to write down: the rule comes back with the cages
+    fn describe(&self, _description: &mut Description) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Check that every cage has between 1 and 9 cells of the grid, and no cell is in a cage twice
+pub(crate) fn check_cages(cages: &[Cage]) -> Result<(), String> {
+    let mut seen = [false; 81];
+    for cage in cages {
+        if cage.cells.is_empty() || cage.cells.len() > 9 {
+            return Err(format!("Cage '{}' needs between 1 and 9 cells", cage));
+        }
+        cage.check_range()?;
+        if let Some(i) = cage.cells.iter().enumerate().find(|(n, i)| cage.cells[..*n].contains(i)).map(|(_, i)| i) {
+            return Err(format!("Cage '{}' has {} twice", cage, cell_name(*i)));
+        }
+        for i in &cage.cells {
+            if seen[*i] {
+                return Err(format!("Cage '{}' overlaps another cage at {}", cage, cell_name(*i)));
+            }
+            seen[*i] = true;
+        }
+    }
+    Ok(())
+}
+
+/// A sudoku with cages on top of the normal rows, columns and blocks.
+/// The cages and the 45 rule are added to the sudoku as constraints.
+pub struct KillerSudoku {
+    sudoku: Sudoku,
+    cages: Vec<Cage>,
+}
+
+impl KillerSudoku {
+    /// Create a killer sudoku from a line of givens (usually all 0) and its cages
+    pub fn new(line: String, cages: Vec<Cage>) -> Result<Self, String> {
+        let mut sudoku = Sudoku::new(line)?;
+        check_cages(&cages)?;
+        for cage in &cages {
+            sudoku.add_constraint(Arc::new(cage.clone()));
+        }
+        let rule = FortyFiveRule::new(&cages, &sudoku.layout)?;
+        sudoku.add_constraint(Arc::new(rule));
+        Ok(KillerSudoku { sudoku, cages })
+    }
+
+    /// Parse a killer sudoku: the first line holds the givens, every following non-empty line a cage
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let line = lines.next().ok_or_else(|| String::from("Killer sudoku is empty"))?;
+        let cages = lines.map(Cage::from_str).collect::<Result<Vec<_>, _>>()?;
+        KillerSudoku::new(line.to_string(), cages)
+    }
+
+    pub fn sudoku(&self) -> &Sudoku {
+        &self.sudoku
+    }
+
+    pub fn cages(&self) -> &[Cage] {
+        &self.cages
+    }
+
+    /// Go through the puzzle once, pruning the cages along with the default techniques
+    /// Returns: the number of moves made
+    pub fn solve_once(&mut self) -> usize {
+        self.sudoku.solve_once()
+    }
+
+    /// Attempt to solve the puzzle, guessing when the techniques get stuck
+    /// Returns true if successfully solved, false otherwise
+    pub fn attempt_solve(&mut self) -> bool {
+        self.sudoku.attempt_solve()
+    }
+
+    /// Check that the puzzle is still valid: no fixed number repeats inside a cage,
+    /// and complete cages add up to their sum
+    pub fn is_valid(&self) -> Result<(), String> {
+        self.sudoku.is_valid()
+    }
+
+    /// Returns true iff. the puzzle is valid & has all numbers filled in
+    pub fn is_solved(&self) -> bool {
+        self.sudoku.is_solved()
+    }
+}
+
+impl Display for KillerSudoku {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        writeln!(f, "{}", self.sudoku.to_line())?;
+        for cage in &self.cages {
+            writeln!(f, "{}", cage)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use crate::sudokutwo::entry_num::{ALL, EntryNumThings};
+    use crate::sudokutwo::killer::{Cage, cage_combinations, FortyFiveRule, KillerSudoku, prune_cage};
+    use crate::sudokutwo::layout::Layout;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const SOLUTION: &str = "417369825632158947958724316825437169791586432346912758289643571573291684164875293";
+
+    /// Cages of three cells next to each other in every row of `SOLUTION`
+    fn row_triples() -> Vec<Cage> {
+        (0..27).map(|t| {
+            let cells = (t * 3..t * 3 + 3).collect::<Vec<_>>();
+            let sum = cells.iter().map(|i| SOLUTION.as_bytes()[*i] as u16 - '0' as u16).sum();
+            Cage { sum, cells }
+        }).collect()
+    }
+
+    #[test]
+    fn parse_cage() {
+        let cage = Cage::from_str("15=r1c1 r1c2 r2c1").unwrap();
+        assert_eq!(cage, Cage { sum: 15, cells: vec!(0, 1, 9) });
+        assert_eq!(cage.to_string(), "15=r1c1 r1c2 r2c1");
+        assert!(Cage::from_str("15=r0c1").is_err());
+        assert!(Cage::from_str("r1c1").is_err());
+    }
+
+    #[test]
+    fn combinations() {
+        assert_eq!(cage_combinations(2, 3).map(|c| c.get_pos()).collect::<Vec<_>>(), vec!(vec!(1, 2)));
+        assert_eq!(cage_combinations(2, 17).map(|c| c.get_pos()).collect::<Vec<_>>(), vec!(vec!(8, 9)));
+        assert_eq!(cage_combinations(3, 6).count(), 1);
+        assert_eq!(cage_combinations(9, 45).count(), 1);
+        assert_eq!(cage_combinations(2, 2).count(), 0);
+    }
+
+    #[test]
+    fn prune_two_cell_cage() {
+        let mut data = [ALL; 81];
+        assert_eq!(prune_cage(&mut data, &[0, 1], 4), 14);
+        assert_eq!(data[0].get_pos(), vec!(1, 3));
+        assert_eq!(data[1].get_pos(), vec!(1, 3));
+    }
+
+    #[test]
+    fn innie() {
+        // the first row has cages on all but its last cell
+        let cages = vec!(
+            Cage::from_str("10=r1c1 r1c2 r1c3 r1c4").unwrap(),
+            Cage::from_str("26=r1c5 r1c6 r1c7 r1c8").unwrap(),
+        );
+        let rule = FortyFiveRule::new(&cages, &Layout::standard()).unwrap();
+        assert!(rule.implied().contains(&Cage { sum: 9, cells: vec!(8) }));
+    }
+
+    #[test]
+    fn outie() {
+        // the cages cover the first row, with one cell sticking out below it
+        let cages = vec!(
+            Cage::from_str("13=r1c1 r1c2 r1c3 r1c4 r2c1").unwrap(),
+            Cage::from_str("35=r1c5 r1c6 r1c7 r1c8 r1c9").unwrap(),
+        );
+        let rule = FortyFiveRule::new(&cages, &Layout::standard()).unwrap();
+        assert!(rule.implied().contains(&Cage { sum: 3, cells: vec!(9) }));
+    }
+
+    #[test]
+    fn overlapping_cages() {
+        let cages = vec!(Cage::from_str("3=r1c1 r1c2").unwrap(), Cage::from_str("4=r1c2 r1c3").unwrap());
+        assert!(KillerSudoku::new("0".repeat(81), cages).is_err());
+    }
+
+    #[test]
+    fn invalid_cages() {
+        let new = |cages: &[&str]| KillerSudoku::new("0".repeat(81), cages.iter().map(|c| Cage::from_str(c).unwrap()).collect())
+            .err();
+        assert_eq!(new(&["3=r1c1 r1c1"]), Some(String::from("Cage '3=r1c1 r1c1' has r1c1 twice")));
+        let out_of_range = KillerSudoku::new("0".repeat(81), vec!(Cage { sum: 3, cells: vec!(0, 81) })).err();
+        assert_eq!(out_of_range, Some(String::from("Cage '3=r1c1 r10c1' has cell 81, but there are only 81")));
+        assert_eq!(new(&["30=r1c1 r1c2 r1c3 r1c4", "20=r1c5 r1c6"]),
+                   Some(String::from("The cages inside r1 add up to 50 instead of 45")));
+        assert_eq!(new(&["10=r1c1 r1c2 r1c3 r1c4 r2c1", "30=r1c5 r1c6 r1c7 r1c8 r1c9"]),
+                   Some(String::from("The cages covering r1 add up to 40, less than 45")));
+    }
+
+    #[test]
+    fn cage_outside_the_grid() {
+        // added straight to a sudoku, the cage isn't checked up front
+        let mut s = Sudoku::new("0".repeat(81)).unwrap();
+        s.add_constraint(Arc::new(Cage { sum: 3, cells: vec!(0, 81) }));
+        assert_eq!(s.is_valid(), Err(String::from("Cage '3=r1c1 r10c1' has cell 81, but there are only 81")));
+        assert!(!s.attempt_solve());
+    }
+
+    #[test]
+    fn solve_without_givens() {
+        let text = std::iter::once("0".repeat(81))
+            .chain(row_triples().iter().map(Cage::to_string))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut killer = KillerSudoku::parse(&text).unwrap();
+        assert!(killer.attempt_solve());
+        assert!(killer.is_solved());
+        for cage in killer.cages() {
+            let sum: u16 = cage.cells.iter().map(|i| killer.sudoku().data[*i].get_fixed()).sum();
+            assert_eq!(sum, cage.sum);
+        }
+    }
+
+    #[test]
+    fn wrong_sum_is_invalid() {
+        let mut cages = row_triples();
+        // the first two rows and blocks still add up to 45, but their cages don't
+        cages[0].sum += 1;
+        cages[1].sum -= 1;
+        cages[3].sum -= 1;
+        cages[4].sum += 1;
+        let killer = KillerSudoku::new(SOLUTION.to_string(), cages).unwrap();
+        assert!(killer.is_valid().is_err());
+    }
+}
@@ -0,0 +1,374 @@
+use std::fmt::{Display, Error, Formatter};
+use std::sync::{Arc, OnceLock};
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+use crate::sudokutwo::sudoku_get_indices::{CELL_UNITS, PEERS, UNITS};
+
+/// A group of 9 cells that has to hold every number exactly once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unit {
+    Row(usize),
+    Col(usize),
+    Box(usize),
+    /// 0 runs from the top left to the bottom right, 1 from the top right to the bottom left
+    Diagonal(usize),
+    /// Windoku regions: 0-3 are the four windows, 4-8 the regions they imply
+    Window(usize),
+}
+
+impl Display for Unit {
+    /// Written 1-based like cell names: `r1`, `c1`, `b1`, and `d1`/`d2` and `w1`-`w9` for diagonals and windows
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Unit::Row(i) => write!(f, "r{}", i + 1),
+            Unit::Col(i) => write!(f, "c{}", i + 1),
+            Unit::Box(i) => write!(f, "b{}", i + 1),
+            Unit::Diagonal(i) => write!(f, "d{}", i + 1),
+            Unit::Window(i) => write!(f, "w{}", i + 1),
+        }
+    }
+}
+
+/// All the units of a puzzle, and for every cell the units it is in and the peers it shares them with.
+/// Techniques only go through the layout, so adding a unit makes every technique respect it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Layout {
+    units: Vec<(Unit, [usize; 9])>,
+    cell_units: Vec<Vec<usize>>,
+    peers: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    /// Create a layout from its units
+    pub fn new(units: Vec<(Unit, [usize; 9])>) -> Result<Self, String> {
+        let mut cell_units = vec![vec!(); 81];
+        for (u, (unit, cells)) in units.iter().enumerate() {
+            for (j, i) in cells.iter().enumerate() {
+                if *i >= 81 || cells[..j].contains(i) {
+                    return Err(format!("{} needs 9 different cells, found {:?}", unit, cells));
+                }
+                cell_units[*i].push(u);
+            }
+        }
+        let peers = cell_units.iter().enumerate()
+            .map(|(i, cell_units)| {
+                let mut peers = cell_units.iter()
+                    .flat_map(|u| units[*u].1.iter().copied())
+                    .filter(|other| *other != i)
+                    .collect::<Vec<_>>();
+                peers.sort_unstable();
+                peers.dedup();
+                peers
+            })
+            .collect();
+        Ok(Layout { units, cell_units, peers })
+    }
+
+    /// The normal rows, columns and blocks
+    pub fn standard() -> Arc<Layout> {
+        static STANDARD: OnceLock<Arc<Layout>> = OnceLock::new();
+        STANDARD.get_or_init(|| Arc::new(Layout {
+            units: standard_units(),
+            cell_units: CELL_UNITS.iter().map(|u| u.to_vec()).collect(),
+            peers: PEERS.iter().map(|p| p.to_vec()).collect(),
+        })).clone()
+    }
+
+    /// Sudoku-X: the standard units plus both main diagonals
+    pub fn diagonal() -> Arc<Layout> {
+        static DIAGONAL: OnceLock<Arc<Layout>> = OnceLock::new();
+        DIAGONAL.get_or_init(|| {
+            let mut units = standard_units();
+            units.push((Unit::Diagonal(0), array_of(|i| i * 10)));
+            units.push((Unit::Diagonal(1), array_of(|i| 8 + i * 8)));
+            Arc::new(Layout::new(units).expect("diagonals are valid units"))
+        }).clone()
+    }
+
+    /// Windoku: the standard units plus four windows at rows and columns 1-3 and 5-7 (counting from 0).
+    /// Together with the rows, columns and blocks they imply five more regions: the cells in rows 0, 4 and 8
+    /// or columns 0, 4 and 8 that line up with the windows, and the nine cells where those rows and columns cross.
+    pub fn windoku() -> Arc<Layout> {
+        static WINDOKU: OnceLock<Arc<Layout>> = OnceLock::new();
+        WINDOKU.get_or_init(|| {
+            let window_lines = [1, 5];
+            let other_lines = [0, 4, 8];
+            let mut groups: Vec<([usize; 3], [usize; 3])> = vec!();
+            for row in window_lines.iter() {
+                for col in window_lines.iter() {
+                    groups.push(([*row, row + 1, row + 2], [*col, col + 1, col + 2]));
+                }
+            }
+            for line in window_lines.iter() {
+                groups.push((other_lines, [*line, line + 1, line + 2]));
+                groups.push(([*line, line + 1, line + 2], other_lines));
+            }
+            groups.push((other_lines, other_lines));
+            let mut units = standard_units();
+            for (w, (rows, cols)) in groups.iter().enumerate() {
+                units.push((Unit::Window(w), array_of(|i| rows[i / 3] * 9 + cols[i % 3])));
+            }
+            Arc::new(Layout::new(units).expect("windows are valid units"))
+        }).clone()
+    }
+
+    /// Jigsaw sudoku: rows and columns, with the blocks replaced by the regions of the map.
+    /// The map has a character for every cell, cells with the same character form a region,
+    /// and every region has to be 9 connected cells.
+    pub fn jigsaw(region_map: &str) -> Result<Layout, String> {
+        let labels = region_map.trim().chars().collect::<Vec<_>>();
+        if labels.len() != 81 {
+            return Err(format!("Region map \'{}\' needs to be 81 characters long", region_map));
+        }
+        let mut regions: Vec<(char, Vec<usize>)> = vec!();
+        for (i, label) in labels.iter().enumerate() {
+            match regions.iter_mut().find(|(l, _)| l == label) {
+                Some((_, cells)) => cells.push(i),
+                None => regions.push((*label, vec!(i))),
+            }
+        }
+        if regions.len() != 9 {
+            return Err(format!("Region map \'{}\' needs 9 regions, found {}", region_map, regions.len()));
+        }
+        let mut units = standard_units();
+        units.retain(|(unit, _)| !matches!(unit, Unit::Box(_)));
+        for (r, (label, cells)) in regions.iter().enumerate() {
+            if cells.len() != 9 {
+                return Err(format!("Region '{}' needs 9 cells, found {}", label, cells.len()));
+            }
+            if !is_connected(cells) {
+                return Err(format!("Region '{}' is not connected", label));
+            }
+            units.push((Unit::Box(r), array_of(|i| cells[i])));
+        }
+        Layout::new(units)
+    }
+
+    pub fn units(&self) -> &[(Unit, [usize; 9])] {
+        &self.units
+    }
+
+    /// The indices (into `units`) of the units the cell is in
+    pub fn cell_units(&self, cell: usize) -> &[usize] {
+        &self.cell_units[cell]
+    }
+
+    /// All the other cells that share a unit with the cell
+    pub fn peers(&self, cell: usize) -> &[usize] {
+        &self.peers[cell]
+    }
+
+    /// The number of the block (or jigsaw region) the cell is in
+    pub fn get_block_index(&self, cell: usize) -> Option<usize> {
+        self.cell_units[cell].iter().find_map(|u| match self.units[*u].0 {
+            Unit::Box(b) => Some(b),
+            _ => None,
+        })
+    }
+
+    /// All the cells of the block (or jigsaw region) the cell is in
+    pub fn get_block_indices_by_cell_index(&self, cell: usize) -> Option<&[usize; 9]> {
+        self.cell_units[cell].iter()
+            .map(|u| &self.units[*u])
+            .find(|(unit, _)| matches!(unit, Unit::Box(_)))
+            .map(|(_, cells)| cells)
+    }
+}
+
+/// Whether the cells form one group when moving up, down, left and right
+fn is_connected(cells: &[usize]) -> bool {
+    let mut reached = vec!(cells[0]);
+    let mut todo = vec!(cells[0]);
+    while let Some(cell) = todo.pop() {
+        let (row, col) = (cell / 9, cell % 9);
+        let neighbours = [
+            (row > 0).then(|| cell - 9),
+            (row < 8).then(|| cell + 9),
+            (col > 0).then(|| cell - 1),
+            (col < 8).then(|| cell + 1),
+        ];
+        for n in neighbours.iter().flatten() {
+            if cells.contains(n) && !reached.contains(n) {
+                reached.push(*n);
+                todo.push(*n);
+            }
+        }
+    }
+    reached.len() == cells.len()
+}
+
+fn standard_units() -> Vec<(Unit, [usize; 9])> {
+    UNITS.iter().enumerate()
+        .map(|(u, cells)| {
+            let unit = match u / 9 {
+                0 => Unit::Row(u % 9),
+                1 => Unit::Col(u % 9),
+                _ => Unit::Box(u % 9),
+            };
+            (unit, *cells)
+        })
+        .collect()
+}
+
+pub(crate) fn array_of(f: impl Fn(usize) -> usize) -> [usize; 9] {
+    let mut res = [0; 9];
+    for (i, cell) in res.iter_mut().enumerate() {
+        *cell = f(i);
+    }
+    res
+}
+
+impl Sudoku {
+    /// Create a sudoku with other units than the standard ones, from a line of text
+    pub fn with_layout(line: String, layout: Arc<Layout>) -> Result<Self, String> {
+        let mut s = Sudoku::new(line)?;
+        s.layout = layout;
+        Ok(s)
+    }
+
+    /// Create a jigsaw sudoku from a line of givens and a line with the region map
+    pub fn with_regions(line: String, region_map: &str) -> Result<Self, String> {
+        Sudoku::with_layout(line, Arc::new(Layout::jigsaw(region_map)?))
+    }
+
+    /// Parse a sudoku from text: a line of givens, optionally followed by a line with a jigsaw region map
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let line = lines.next().ok_or_else(|| String::from("Sudoku is empty"))?;
+        match lines.next() {
+            Some(region_map) => Sudoku::with_regions(line.to_string(), region_map),
+            None => Sudoku::new(line.to_string()),
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::entry_num::{EntryNumThings, FIVE};
+    use crate::sudokutwo::layout::{Layout, Unit};
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+    use crate::sudokutwo::technique::{NakedSingles, NakedSubsets, Technique};
+
+    #[test]
+    fn standard_matches_generated() {
+        let generated = Layout::new(Layout::standard().units().to_vec()).unwrap();
+        assert_eq!(*Layout::standard(), generated);
+    }
+
+    #[test]
+    fn diagonal_peers() {
+        let layout = Layout::diagonal();
+        assert_eq!(layout.cell_units(40).len(), 5);
+        assert_eq!(layout.peers(0).len(), 26);
+        assert_eq!(layout.peers(1).len(), 20);
+        assert!(layout.peers(0).contains(&80));
+    }
+
+    #[test]
+    fn techniques_use_diagonals() {
+        let line = format!("5{}", "0".repeat(80));
+        for technique in [&NakedSingles as &dyn Technique, &NakedSubsets].iter() {
+            let mut s = Sudoku::with_layout(line.clone(), Layout::diagonal()).unwrap();
+            technique.apply(&mut s);
+            assert_eq!(s.data[80] & FIVE, 0);
+            assert_eq!(s.data[79].get_pos().len(), 9);
+        }
+    }
+
+    const JIGSAW_REGIONS: &str = concat!(
+        "AAABBCCCC", "ADABBBBCC", "ADDBBBCCF", "ADDEEEFCF", "AADEEFFFF",
+        "DDDHEEEFF", "GGGHEHIII", "GGGHHHHII", "GGGHHIIII",
+    );
+
+    #[test]
+    fn jigsaw_regions() {
+        let layout = Layout::jigsaw(JIGSAW_REGIONS).unwrap();
+        assert_eq!(layout.units().len(), 27);
+        assert_eq!(layout.get_block_index(0), Some(0));
+        // regions are numbered in the order they first appear: A, B, C, D, F, E, ...
+        assert_eq!(layout.get_block_index(30), Some(5));
+        assert!(layout.get_block_indices_by_cell_index(30).unwrap().contains(&50));
+        // the standard layout still gives the normal blocks
+        assert_eq!(Layout::standard().get_block_index(30), Some(4));
+    }
+
+    #[test]
+    fn invalid_jigsaw() {
+        // a region of 10 cells and one of 8
+        let too_big = JIGSAW_REGIONS.replacen('B', "A", 1);
+        assert!(Layout::jigsaw(&too_big).is_err());
+        assert!(Layout::jigsaw("ABC").is_err());
+        // swapping the corners splits regions A and I in two
+        let split = format!("I{}A", &JIGSAW_REGIONS[1..80]);
+        assert!(Layout::jigsaw(&split).is_err());
+    }
+
+    #[test]
+    fn solve_jigsaw() {
+        let text = format!("{}\n{}", "0".repeat(81), JIGSAW_REGIONS);
+        let mut s = Sudoku::parse(&text).unwrap();
+        assert!(s.attempt_solve());
+        let solution = s.to_line();
+        for (_, cells) in Layout::jigsaw(JIGSAW_REGIONS).unwrap().units() {
+            let mut digits = cells.iter().map(|i| solution.as_bytes()[*i]).collect::<Vec<_>>();
+            digits.sort_unstable();
+            assert_eq!(digits, b"123456789".to_vec());
+        }
+    }
+
+    #[test]
+    fn windoku_windows() {
+        let layout = Layout::windoku();
+        assert_eq!(layout.units().len(), 36);
+        let (unit, cells) = layout.units()[27];
+        assert_eq!(unit, Unit::Window(0));
+        assert_eq!(cells, [10, 11, 12, 19, 20, 21, 28, 29, 30]);
+        // every cell is in exactly one window
+        for cell in 0..81 {
+            let windows = layout.cell_units(cell).iter()
+                .filter(|u| matches!(layout.units()[**u].0, Unit::Window(_)))
+                .count();
+            assert_eq!(windows, 1);
+        }
+    }
+
+    #[test]
+    fn solve_windoku() {
+        let mut s = Sudoku::with_layout("0".repeat(81), Layout::windoku()).unwrap();
+        assert!(s.attempt_solve());
+        let solution = s.to_line();
+        for (_, cells) in Layout::windoku().units() {
+            let mut digits = cells.iter().map(|i| solution.as_bytes()[*i]).collect::<Vec<_>>();
+            digits.sort_unstable();
+            assert_eq!(digits, b"123456789".to_vec());
+        }
+    }
+
+    #[test]
+    fn invalid_unit() {
+        let mut units = Layout::standard().units().to_vec();
+        units.push((Unit::Diagonal(0), [0, 0, 1, 2, 3, 4, 5, 6, 7]));
+        assert!(Layout::new(units).is_err());
+        assert!(Layout::new(vec!((Unit::Row(0), [0, 1, 2, 3, 4, 5, 6, 7, 81]))).is_err());
+    }
+
+    #[test]
+    fn solve_sudoku_x() {
+        // an almost empty grid, so the solution has to come out of the units alone
+        let line = "000000000000000000000000000000000000000000000000000000000000000000000000123456789";
+        let mut s = Sudoku::with_layout(line.to_string(), Layout::diagonal()).unwrap();
+        assert!(s.attempt_solve());
+        let solution = s.to_line();
+        for d in [(0..9).map(|i| i * 10).collect::<Vec<_>>(), (0..9).map(|i| 8 + i * 8).collect()].iter() {
+            let mut digits = d.iter().map(|i| solution.as_bytes()[*i]).collect::<Vec<_>>();
+            digits.sort_unstable();
+            assert_eq!(digits, b"123456789".to_vec());
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::convert::TryFrom;
+use std::iter;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::constraint::{cell_name, Constraint, cross_out, parse_cells, prune_pair, prune_sum};
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{self, ALL, EntryNum, EntryNumThings};
+use crate::sudokutwo::killer::combination_fits;
+
+/// Check that the cells of a line exist and don't repeat
+fn check_line(cells: &[usize], what: &str) -> Result<(), String> {
+    if let Some(cell) = cells.iter().find(|i| **i >= 81) {
+        return Err(format!("A {} can't go through cell {}", what, cell));
+    }
+    for (i, cell) in cells.iter().enumerate() {
+        if cells[..i].contains(cell) {
+            return Err(format!("A {} goes through {} twice", what, cell_name(*cell)));
+        }
+    }
+    Ok(())
+}
+
+/// The numbers on a thermometer increase from the bulb (the first cell) to the end
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Thermometer {
+    cells: Vec<usize>,
+}
+
+impl Thermometer {
+    pub fn new(cells: Vec<usize>) -> Result<Self, String> {
+        if !(2..=9).contains(&cells.len()) {
+            return Err(format!("A thermometer needs between 2 and 9 cells, found {}", cells.len()));
+        }
+        check_line(&cells, "thermometer")?;
+        Ok(Thermometer { cells })
+    }
+
+    /// The cells from the bulb to the end
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+impl Constraint for Thermometer {
+    /// Every cell is larger than the smallest possibility before it, and smaller than the largest after it
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let mut res = 0;
+        for pair in self.cells.windows(2) {
+            let low = data[pair[0]].lowest().unwrap_or(9);
+            res += cross_out(data, pair[1], entry_num::range(1, low))?;
+        }
+        for pair in self.cells.windows(2).rev() {
+            let high = data[pair[1]].highest().unwrap_or(1);
+            res += cross_out(data, pair[0], entry_num::range(high, 9))?;
+        }
+        Ok(res)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.thermometers.push(self.clone());
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<String>> for Thermometer {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        Thermometer::new(parse_cells(&names)?)
+    }
+}
+
+impl From<Thermometer> for Vec<String> {
+    fn from(thermometer: Thermometer) -> Self {
+        thermometer.cells.into_iter().map(cell_name).collect()
+    }
+}
+
+/// The numbers along an arrow add up to the number in its circle.
+/// Numbers may repeat along the arrow, as long as the rows, columns and blocks allow it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Arrow {
+    circle: usize,
+    cells: Vec<usize>,
+}
+
+impl Arrow {
+    pub fn new(circle: usize, cells: Vec<usize>) -> Result<Self, String> {
+        if cells.is_empty() {
+            return Err(format!("The arrow from {} needs at least one cell", cell_name(circle)));
+        }
+        check_line(&iter::once(circle).chain(cells.iter().copied()).collect::<Vec<_>>(), "arrow")?;
+        Ok(Arrow { circle, cells })
+    }
+
+    pub fn circle(&self) -> usize {
+        self.circle
+    }
+
+    /// The cells along the arrow, starting next to the circle
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+impl Constraint for Arrow {
+    /// The circle has to lie between the smallest and largest possible sums of the arrow,
+    /// and every cell of the arrow has to leave room for the others to reach the circle
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        cross_out(data, self.circle, 0)?;
+        let min_sum: u16 = self.cells.iter().map(|i| data[*i].lowest().unwrap_or(0)).sum();
+        let max_sum: u16 = self.cells.iter().map(|i| data[*i].highest().unwrap_or(0)).sum();
+        let mut res = cross_out(data, self.circle, ALL & !entry_num::range(min_sum, max_sum))?;
+        let circle = data[self.circle];
+        res += prune_sum(data, &self.cells, circle.lowest().unwrap_or(0), circle.highest().unwrap_or(0))?;
+        Ok(res)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.arrows.push(self.clone());
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<String>> for Arrow {
+    type Error = String;
+
+    /// The first cell is the circle, the others follow the arrow
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        let cells = parse_cells(&names)?;
+        match cells.split_first() {
+            Some((circle, cells)) => Arrow::new(*circle, cells.to_vec()),
+            None => Err(String::from("An arrow needs a circle")),
+        }
+    }
+}
+
+impl From<Arrow> for Vec<String> {
+    fn from(arrow: Arrow) -> Self {
+        iter::once(arrow.circle).chain(arrow.cells).map(cell_name).collect()
+    }
+}
+
+/// The fixed numbers on a line that can't repeat
+fn check_repeats(data: &[EntryNum; 81], cells: &[usize], what: &str) -> Result<(), String> {
+    let mut fixed: EntryNum = 0;
+    for cell in cells.iter().filter(|i| data[**i].is_fixed()) {
+        if fixed & data[*cell] > 0 {
+            return Err(format!("The {} through {} repeats {}", what, cell_name(cells[0]), data[*cell].get_fixed()));
+        }
+        fixed |= data[*cell];
+    }
+    Ok(())
+}
+
+/// Neighbours on a German whispers line differ by at least 5
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Whispers {
+    cells: Vec<usize>,
+}
+
+impl Whispers {
+    pub fn new(cells: Vec<usize>) -> Result<Self, String> {
+        if cells.len() < 2 {
+            return Err(format!("A whispers line needs at least 2 cells, found {}", cells.len()));
+        }
+        check_line(&cells, "whispers line")?;
+        Ok(Whispers { cells })
+    }
+
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+impl Constraint for Whispers {
+    /// Every number needs a neighbour at least 5 away, which also rules out 5 everywhere on the line
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let mut res = 0;
+        for pair in self.cells.windows(2) {
+            res += prune_pair(data, pair[0], pair[1], |a, b| a.max(b) - a.min(b) >= 5)?;
+        }
+        Ok(res)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.whispers.push(self.clone());
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<String>> for Whispers {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        Whispers::new(parse_cells(&names)?)
+    }
+}
+
+impl From<Whispers> for Vec<String> {
+    fn from(whispers: Whispers) -> Self {
+        whispers.cells.into_iter().map(cell_name).collect()
+    }
+}
+
+/// A renban line holds a set of consecutive numbers, in any order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Renban {
+    cells: Vec<usize>,
+}
+
+impl Renban {
+    pub fn new(cells: Vec<usize>) -> Result<Self, String> {
+        if !(2..=9).contains(&cells.len()) {
+            return Err(format!("A renban line needs between 2 and 9 cells, found {}", cells.len()));
+        }
+        check_line(&cells, "renban line")?;
+        Ok(Renban { cells })
+    }
+
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+impl Constraint for Renban {
+    /// Try every run of consecutive numbers as long as the line:
+    /// a number stays possible when it is part of a run that fits
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        check_repeats(data, &self.cells, "renban line")?;
+        let len = self.cells.len() as u16;
+        let runs = (1..=10 - len)
+            .map(|low| entry_num::range(low, low + len - 1))
+            .filter(|run| combination_fits(data, &self.cells, *run))
+            .fold(0, |cur, run| cur | run);
+        let mut res = 0;
+        for cell in self.cells.iter() {
+            res += cross_out(data, *cell, ALL & !runs)?;
+        }
+        Ok(res)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.renban.push(self.clone());
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<String>> for Renban {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        Renban::new(parse_cells(&names)?)
+    }
+}
+
+impl From<Renban> for Vec<String> {
+    fn from(renban: Renban) -> Self {
+        renban.cells.into_iter().map(cell_name).collect()
+    }
+}
+
+/// A palindrome line reads the same from both ends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Palindrome {
+    cells: Vec<usize>,
+}
+
+impl Palindrome {
+    pub fn new(cells: Vec<usize>) -> Result<Self, String> {
+        if cells.len() < 2 {
+            return Err(format!("A palindrome needs at least 2 cells, found {}", cells.len()));
+        }
+        check_line(&cells, "palindrome")?;
+        Ok(Palindrome { cells })
+    }
+
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+impl Constraint for Palindrome {
+    /// Cells at the same distance from both ends can only hold what both of them can
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let mut res = 0;
+        for (a, b) in self.cells.iter().zip(self.cells.iter().rev()).take(self.cells.len() / 2) {
+            let both = data[*a] & data[*b];
+            res += cross_out(data, *a, ALL & !both)?;
+            res += cross_out(data, *b, ALL & !both)?;
+        }
+        Ok(res)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.palindromes.push(self.clone());
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<String>> for Palindrome {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        Palindrome::new(parse_cells(&names)?)
+    }
+}
+
+impl From<Palindrome> for Vec<String> {
+    fn from(palindrome: Palindrome) -> Self {
+        palindrome.cells.into_iter().map(cell_name).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::constraint::Constraint;
+    use crate::sudokutwo::entry_num::{ALL, EntryNumThings, FIVE, FOUR, NINE, ONE, SEVEN, SIX, TWO};
+    use crate::sudokutwo::lines::{Arrow, Palindrome, Renban, Thermometer, Whispers};
+
+    #[test]
+    fn thermometer_bounds() {
+        let mut data = [ALL; 81];
+        data[1] = FIVE;
+        let thermometer = Thermometer::new(vec!(0, 1, 2)).unwrap();
+        assert!(thermometer.prune(&mut data).is_ok());
+        assert_eq!(data[0].get_pos(), vec!(1, 2, 3, 4));
+        assert_eq!(data[2].get_pos(), vec!(6, 7, 8, 9));
+        assert!(Thermometer::new(vec!(0)).is_err());
+        assert!(Thermometer::new(vec!(0, 1, 0)).is_err());
+    }
+
+    #[test]
+    fn thermometer_too_long_for_a_cell() {
+        let mut data = [ALL; 81];
+        data[2] = TWO;
+        assert!(Thermometer::new(vec!(0, 1, 2)).unwrap().check(&data).is_err());
+    }
+
+    #[test]
+    fn arrow_bounds() {
+        let mut data = [ALL; 81];
+        data[40] = SEVEN;
+        data[30] = TWO;
+        let arrow = Arrow::new(40, vec!(30, 20)).unwrap();
+        assert!(arrow.prune(&mut data).is_ok());
+        assert_eq!(data[20].get_pos(), vec!(5));
+
+        let mut data = [ALL; 81];
+        let arrow = Arrow::new(40, vec!(30, 20, 10)).unwrap();
+        assert!(arrow.prune(&mut data).is_ok());
+        assert_eq!(data[40].get_pos(), vec!(3, 4, 5, 6, 7, 8, 9));
+        assert_eq!(data[30].get_pos(), vec!(1, 2, 3, 4, 5, 6, 7));
+    }
+
+    #[test]
+    fn whispers() {
+        let mut data = [ALL; 81];
+        let line = Whispers::new(vec!(0, 1, 2)).unwrap();
+        assert!(line.prune(&mut data).is_ok());
+        assert_eq!(data[1].get_pos(), vec!(1, 2, 3, 4, 6, 7, 8, 9));
+        data[1] = FOUR;
+        assert!(line.prune(&mut data).is_ok());
+        assert_eq!(data[0].get_pos(), vec!(9));
+        assert_eq!(data[2].get_pos(), vec!(9));
+    }
+
+    #[test]
+    fn renban() {
+        let mut data = [ALL; 81];
+        data[0] = ONE;
+        let line = Renban::new(vec!(0, 1, 2)).unwrap();
+        assert!(line.prune(&mut data).is_ok());
+        assert_eq!(data[1].get_pos(), vec!(1, 2, 3));
+        data[3] = NINE;
+        data[4] = SIX;
+        assert!(Renban::new(vec!(3, 4)).unwrap().check(&data).is_err());
+    }
+
+    #[test]
+    fn palindrome() {
+        let mut data = [ALL; 81];
+        data[0] = ONE | TWO;
+        data[30] = TWO | SIX;
+        let line = Palindrome::new(vec!(0, 10, 20, 30)).unwrap();
+        assert!(line.prune(&mut data).is_ok());
+        assert_eq!(data[0], TWO);
+        assert_eq!(data[30], TWO);
+        assert_eq!(data[10], ALL);
+    }
+}
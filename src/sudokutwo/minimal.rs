@@ -0,0 +1,110 @@
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::entry_num::ALL;
+use crate::sudokutwo::origin::Origin;
+
+/// How much a puzzle with a unique solution leans on each of its givens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Minimality {
+    /// The givens that can each be left out on their own while the solution stays unique
+    pub redundant: Vec<usize>,
+}
+
+impl Minimality {
+    /// Returns true iff. removing any single given makes the solution non-unique
+    pub fn is_minimal(&self) -> bool {
+        self.redundant.is_empty()
+    }
+}
+
+impl Sudoku {
+    /// The puzzle with only the given cells filled in, everything else open again
+    pub(crate) fn with_givens(&self, givens: &[usize]) -> Sudoku {
+        let mut res = self.clone();
+        for i in (0..81).filter(|i| !givens.contains(i)) {
+            res.data[i] = ALL;
+            res.origins[i] = Origin::Logic;
+        }
+        res
+    }
+
+    /// Returns: an error if the puzzle has no solution or more than one
+    pub(crate) fn check_unique(&self) -> Result<(), String> {
+        match self.count_solutions(2) {
+            1 => Ok(()),
+            0 => Err(String::from("The puzzle has no solution")),
+            _ => Err(String::from("The puzzle has more than one solution")),
+        }
+    }
+
+    /// Find the givens that can be removed one at a time without losing the unique solution
+    /// Returns: the analysis, or an error if the puzzle doesn't have a unique solution to begin with
+    pub fn minimality(&self) -> Result<Minimality, String> {
+        let givens = self.givens();
+        let puzzle = self.with_givens(&givens);
+        puzzle.check_unique()?;
+        let redundant = givens.iter()
+            .filter(|given| {
+                let others = givens.iter().copied().filter(|i| i != *given).collect::<Vec<_>>();
+                puzzle.with_givens(&others).count_solutions(2) == 1
+            })
+            .copied()
+            .collect();
+        Ok(Minimality { redundant })
+    }
+
+    /// Strip givens until the puzzle is minimal, trying them from the top left.
+    /// One pass is enough: a given that had to stay with more givens around has to stay with fewer.
+    /// Returns: the minimal puzzle, or an error if the puzzle doesn't have a unique solution to begin with
+    pub fn minimise(&self) -> Result<Sudoku, String> {
+        let mut givens = self.givens();
+        self.with_givens(&givens).check_unique()?;
+        let mut i = 0;
+        while i < givens.len() {
+            let removed = givens.remove(i);
+            if self.with_givens(&givens).count_solutions(2) != 1 {
+                givens.insert(i, removed);
+                i += 1;
+            }
+        }
+        Ok(self.with_givens(&givens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+
+    #[test]
+    fn easy_is_not_minimal() {
+        let easy = Sudoku::new(String::from(EASY)).unwrap();
+        let minimality = easy.minimality().unwrap();
+        assert!(!minimality.is_minimal());
+        for cell in minimality.redundant.iter() {
+            let mut line = String::from(EASY);
+            line.replace_range(*cell..*cell + 1, "0");
+            assert_eq!(Sudoku::new(line).unwrap().count_solutions(2), 1);
+        }
+    }
+
+    #[test]
+    fn minimise_keeps_the_solution() {
+        let easy = Sudoku::new(String::from(EASY)).unwrap();
+        let minimal = easy.minimise().unwrap();
+        assert!(minimal.givens().len() < easy.givens().len());
+        assert!(minimal.minimality().unwrap().is_minimal());
+        let (mut a, mut b) = (easy.clone(), minimal.clone());
+        assert!(a.attempt_solve() && b.attempt_solve());
+        assert_eq!(a.to_line(), b.to_line());
+        assert_eq!(minimal.minimise().unwrap().to_line(), minimal.to_line());
+    }
+
+    #[test]
+    fn needs_a_unique_solution() {
+        let empty = Sudoku::new("0".repeat(81)).unwrap();
+        assert!(empty.minimality().is_err());
+        assert!(Sudoku::new(format!("11{}", "0".repeat(79))).unwrap().minimise().is_err());
+    }
+}
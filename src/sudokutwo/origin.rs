@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::constraint::cell_name;
+use crate::sudokutwo::entry_num::{self, ALL, EntryNum, EntryNumThings};
+
+/// Where the number in a cell came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// A clue of the puzzle
+    Given,
+    /// Deduced by the techniques
+    Logic,
+    /// Tried by the search, and kept because it led to a solution
+    Guess,
+    /// Filled in by the player
+    User,
+}
+
+impl Sudoku {
+    /// Where the number in the cell came from.
+    /// Only givens, guesses and the player's numbers are written down, any other filled in cell was deduced.
+    /// Returns: None if the cell isn't filled in yet
+    pub fn origin(&self, cell: usize) -> Option<Origin> {
+        if self.data[cell].is_fixed() {
+            Some(self.origins[cell])
+        } else {
+            None
+        }
+    }
+
+    /// The cells holding the clues of the puzzle
+    pub fn givens(&self) -> Vec<usize> {
+        (0..81).filter(|i| self.origin(*i) == Some(Origin::Given)).collect()
+    }
+
+    /// The puzzle as a line, with only the givens filled in
+    pub fn givens_line(&self) -> String {
+        (0..81)
+            .map(|i| match self.origin(i) {
+                Some(Origin::Given) => (b'0' + self.data[i].get_fixed() as u8) as char,
+                _ => '0',
+            })
+            .collect()
+    }
+
+    /// Fill in a number for the player, whether or not it is still possible there
+    /// Returns: an error if the cell holds a given or the number doesn't exist
+    pub fn place(&mut self, cell: usize, num: u16) -> Result<(), String> {
+        if cell >= 81 {
+            return Err(format!("Cell {} doesn't exist", cell));
+        }
+        if !(1..=9).contains(&num) {
+            return Err(format!("Can't place {} at {}", num, cell_name(cell)));
+        }
+        if self.origin(cell) == Some(Origin::Given) {
+            return Err(format!("{} holds a given", cell_name(cell)));
+        }
+        self.data[cell] = entry_num::to_entry_num(num);
+        self.origins[cell] = Origin::User;
+        Ok(())
+    }
+
+    /// Erase the player's number from the cell, leaving every number possible again
+    /// Returns: an error if the cell holds a given
+    pub fn clear(&mut self, cell: usize) -> Result<(), String> {
+        if cell >= 81 {
+            return Err(format!("Cell {} doesn't exist", cell));
+        }
+        if self.origin(cell) == Some(Origin::Given) {
+            return Err(format!("{} holds a given", cell_name(cell)));
+        }
+        self.data[cell] = ALL;
+        self.origins[cell] = Origin::Logic;
+        Ok(())
+    }
+
+    /// Fill in a number, writing down where it came from
+    pub(crate) fn fill(&mut self, cell: usize, num: EntryNum, origin: Origin) {
+        self.data[cell] = num;
+        self.origins[cell] = origin;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::origin::Origin;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+
+    #[test]
+    fn givens_stay_apart() {
+        let mut s = Sudoku::new(String::from(EASY)).unwrap();
+        assert_eq!(s.origin(0), None);
+        assert_eq!(s.origin(2), Some(Origin::Given));
+        assert!(s.attempt_solve());
+        assert_eq!(s.origin(0), Some(Origin::Logic));
+        assert_eq!(s.origin(2), Some(Origin::Given));
+        assert_eq!(s.givens_line(), EASY);
+        assert_eq!(s.givens().len(), EASY.chars().filter(|c| *c != '0').count());
+        let bold = format!("{:#}", s);
+        assert!(bold.starts_with("48\x1b[1m3\x1b[0m9\x1b[1m2\x1b[0m1"));
+        assert_eq!(bold.lines().count(), 9);
+        assert!(!s.to_string().contains('\x1b'));
+    }
+
+    #[test]
+    fn search_marks_guesses() {
+        // nothing to deduce from an empty grid, so the search has to guess
+        let mut s = Sudoku::new("0".repeat(81)).unwrap();
+        assert!(s.attempt_solve());
+        assert!((0..81).any(|i| s.origin(i) == Some(Origin::Guess)));
+        assert!(s.givens().is_empty());
+    }
+
+    #[test]
+    fn player_numbers() {
+        let mut s = Sudoku::new(String::from(EASY)).unwrap();
+        assert_eq!(s.place(2, 4), Err(String::from("r1c3 holds a given")));
+        assert_eq!(s.place(0, 10), Err(String::from("Can't place 10 at r1c1")));
+        assert_eq!(s.place(81, 1), Err(String::from("Cell 81 doesn't exist")));
+        assert_eq!(s.place(0, 4), Ok(()));
+        assert_eq!(s.origin(0), Some(Origin::User));
+        assert_eq!(s.givens_line(), EASY);
+        assert_eq!(s.clear(2), Err(String::from("r1c3 holds a given")));
+        assert_eq!(s.clear(0), Ok(()));
+        assert_eq!(s.origin(0), None);
+    }
+}
@@ -0,0 +1,349 @@
+use std::convert::TryFrom;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::constraint::{cell_name, Constraint, cross_out, parse_cell, prune_sum};
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{ALL, EntryNum, NINE, ONE};
+use crate::sudokutwo::killer::{cage_combinations, combination_fits};
+use crate::sudokutwo::layout::Unit;
+
+/// The largest sum between a 1 and a 9: all of 2 up to 8
+const MAX_SANDWICH: u16 = 35;
+
+/// A clue outside a row or column: the numbers between its 1 and its 9 add up to `sum`.
+/// Written as `<sum>=r<row>` or `<sum>=c<col>` (1-based), for example `16=r1`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Sandwich {
+    line: Unit,
+    sum: u16,
+}
+
+impl Sandwich {
+    /// A sandwich clue for a row or a column, both 0-based
+    pub fn new(line: Unit, sum: u16) -> Result<Self, String> {
+        match line {
+            Unit::Row(i) | Unit::Col(i) if i < 9 => {}
+            _ => return Err(format!("A sandwich clue belongs to a row or column, not {}", line)),
+        }
+        if sum > MAX_SANDWICH {
+            return Err(format!("A sandwich can't add up to more than {}, found {}", MAX_SANDWICH, sum));
+        }
+        Ok(Sandwich { line, sum })
+    }
+
+    pub fn line(&self) -> Unit {
+        self.line
+    }
+
+    pub fn sum(&self) -> u16 {
+        self.sum
+    }
+
+    /// The cells of the row or column, in order
+    pub fn cells(&self) -> [usize; 9] {
+        let mut res = [0; 9];
+        for (i, cell) in res.iter_mut().enumerate() {
+            *cell = match self.line {
+                Unit::Col(col) => i * 9 + col,
+                _ => self.line_index() * 9 + i,
+            };
+        }
+        res
+    }
+
+    fn line_index(&self) -> usize {
+        match self.line {
+            Unit::Row(i) | Unit::Col(i) => i,
+            _ => unreachable!("checked in Sandwich::new"),
+        }
+    }
+}
+
+impl Constraint for Sandwich {
+    /// Try every place for the 1 and the 9, and every set of numbers that fits between them:
+    /// a number stays possible when it is used by at least one of them
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        let cells = self.cells();
+        let mut allowed: [EntryNum; 9] = [0; 9];
+        for one in (0..9).filter(|i| data[cells[*i]] & ONE > 0) {
+            for nine in (0..9).filter(|i| *i != one && data[cells[*i]] & NINE > 0) {
+                let between = one.min(nine) + 1..one.max(nine);
+                let fillings = match between.len() {
+                    0 if self.sum == 0 => vec!(0),
+                    0 => vec!(),
+                    len => cage_combinations(len, self.sum)
+                        .filter(|c| c & (ONE | NINE) == 0 && combination_fits(data, &cells[between.clone()], *c))
+                        .collect(),
+                };
+                for filling in fillings {
+                    for (i, a) in allowed.iter_mut().enumerate() {
+                        *a |= data[cells[i]] & if i == one {
+                            ONE
+                        } else if i == nine {
+                            NINE
+                        } else if between.contains(&i) {
+                            filling
+                        } else {
+                            ALL & !(ONE | NINE)
+                        };
+                    }
+                }
+            }
+        }
+        let mut res = 0;
+        for (cell, a) in cells.iter().zip(allowed.iter()) {
+            res += cross_out(data, *cell, ALL & !a)
+                .map_err(|_| format!("The sandwich '{}' can't be made anymore", self))?;
+        }
+        Ok(res)
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.sandwiches.push(self.clone());
+        Ok(())
+    }
+}
+
+impl FromStr for Sandwich {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sum, line) = s.split_once('=')
+            .ok_or_else(|| format!("Sandwich '{}' needs to be written as <sum>=<row or column>", s))?;
+        let sum = sum.trim().parse::<u16>()
+            .map_err(|_| format!("Sandwich '{}' has an invalid sum '{}'", s, sum.trim()))?;
+        let line = line.trim();
+        let index = line.get(1..).and_then(|i| i.parse::<usize>().ok()).filter(|i| (1..=9).contains(i))
+            .ok_or_else(|| format!("Sandwich '{}' has an invalid row or column '{}'", s, line))?;
+        match line.chars().next() {
+            Some('r') => Sandwich::new(Unit::Row(index - 1), sum),
+            Some('c') => Sandwich::new(Unit::Col(index - 1), sum),
+            _ => Err(format!("Sandwich '{}' has an invalid row or column '{}'", s, line)),
+        }
+    }
+}
+
+impl Display for Sandwich {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let kind = match self.line {
+            Unit::Col(_) => 'c',
+            _ => 'r',
+        };
+        write!(f, "{}={}{}", self.sum, kind, self.line_index() + 1)
+    }
+}
+
+impl TryFrom<String> for Sandwich {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Sandwich> for String {
+    fn from(sandwich: Sandwich) -> Self {
+        sandwich.to_string()
+    }
+}
+
+/// The way a little killer clue points into the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl Direction {
+    fn step(self) -> (isize, isize) {
+        match self {
+            Direction::NorthEast => (-1, 1),
+            Direction::NorthWest => (-1, -1),
+            Direction::SouthEast => (1, 1),
+            Direction::SouthWest => (1, -1),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ne" => Ok(Direction::NorthEast),
+            "nw" => Ok(Direction::NorthWest),
+            "se" => Ok(Direction::SouthEast),
+            "sw" => Ok(Direction::SouthWest),
+            _ => Err(format!("'{}' is not a direction, use ne, nw, se or sw", s)),
+        }
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        let name = match self {
+            Direction::NorthEast => "ne",
+            Direction::NorthWest => "nw",
+            Direction::SouthEast => "se",
+            Direction::SouthWest => "sw",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A clue outside the grid pointing along a diagonal: the numbers on it add up to `sum`, and may repeat.
+/// Written as `<sum>=<first cell> <direction>`, for example `37=r1c2 se` for the diagonal from r1c2 to r8c9.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LittleKiller {
+    sum: u16,
+    direction: Direction,
+    cells: Vec<usize>,
+}
+
+impl LittleKiller {
+    /// A clue for the diagonal that starts at `first` and goes in `direction` until it leaves the grid
+    pub fn new(sum: u16, first: usize, direction: Direction) -> Result<Self, String> {
+        if first >= 81 {
+            return Err(format!("Cell {} doesn't exist", first));
+        }
+        let (dr, dc) = direction.step();
+        let (mut row, mut col) = ((first / 9) as isize, (first % 9) as isize);
+        let mut cells = vec!();
+        while (0..9).contains(&row) && (0..9).contains(&col) {
+            cells.push((row * 9 + col) as usize);
+            row += dr;
+            col += dc;
+        }
+        let (low, high) = (cells.len() as u16, cells.len() as u16 * 9);
+        if !(low..=high).contains(&sum) {
+            return Err(format!("The diagonal {} {} can't add up to {}", cell_name(first), direction, sum));
+        }
+        Ok(LittleKiller { sum, direction, cells })
+    }
+
+    pub fn sum(&self) -> u16 {
+        self.sum
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The cells along the diagonal, starting next to the clue
+    pub fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+}
+
+impl Constraint for LittleKiller {
+    fn prune(&self, data: &mut [EntryNum; 81]) -> Result<u32, String> {
+        prune_sum(data, &self.cells, self.sum, self.sum)
+            .map_err(|_| format!("The diagonal '{}' can't add up to its sum anymore", self))
+    }
+
+    fn describe(&self, description: &mut Description) -> Result<(), String> {
+        description.little_killers.push(self.clone());
+        Ok(())
+    }
+}
+
+impl FromStr for LittleKiller {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (sum, rest) = s.split_once('=')
+            .ok_or_else(|| format!("Little killer '{}' needs to be written as <sum>=<cell> <direction>", s))?;
+        let sum = sum.trim().parse::<u16>()
+            .map_err(|_| format!("Little killer '{}' has an invalid sum '{}'", s, sum.trim()))?;
+        match rest.split_whitespace().collect::<Vec<_>>()[..] {
+            [first, direction] => {
+                let first = parse_cell(first).ok_or_else(|| format!("Little killer '{}' has an invalid cell '{}'", s, first))?;
+                LittleKiller::new(sum, first, direction.parse()?)
+            }
+            _ => Err(format!("Little killer '{}' needs to be written as <sum>=<cell> <direction>", s)),
+        }
+    }
+}
+
+impl Display for LittleKiller {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}={} {}", self.sum, cell_name(self.cells[0]), self.direction)
+    }
+}
+
+impl TryFrom<String> for LittleKiller {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<LittleKiller> for String {
+    fn from(little_killer: LittleKiller) -> Self {
+        little_killer.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::sudokutwo::constraint::Constraint;
+    use crate::sudokutwo::entry_num::{ALL, EntryNumThings, ONE};
+    use crate::sudokutwo::layout::Unit;
+    use crate::sudokutwo::outside::{Direction, LittleKiller, Sandwich};
+
+    #[test]
+    fn parse_clues() {
+        let sandwich = Sandwich::from_str("16=r1").unwrap();
+        assert_eq!(sandwich, Sandwich::new(Unit::Row(0), 16).unwrap());
+        assert_eq!(sandwich.to_string(), "16=r1");
+        assert_eq!(Sandwich::from_str("0=c9").unwrap().cells()[8], 80);
+        assert!(Sandwich::from_str("36=r1").is_err());
+        assert!(Sandwich::from_str("3=b1").is_err());
+
+        let little_killer = LittleKiller::from_str("37=r1c2 se").unwrap();
+        assert_eq!(little_killer.cells(), &[1, 11, 21, 31, 41, 51, 61, 71]);
+        assert_eq!(little_killer.to_string(), "37=r1c2 se");
+        assert_eq!(LittleKiller::new(3, 7, Direction::SouthEast).unwrap().cells(), &[7, 17]);
+        assert!(LittleKiller::from_str("20=r1c8 se").is_err());
+        assert!(LittleKiller::from_str("5=r1c1 up").is_err());
+    }
+
+    #[test]
+    fn widest_sandwich() {
+        // 2 up to 8 between: the 1 and 9 are at the ends
+        let mut data = [ALL; 81];
+        assert!(Sandwich::new(Unit::Row(0), 35).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[0].get_pos(), vec!(1, 9));
+        assert_eq!(data[4].get_pos(), vec!(2, 3, 4, 5, 6, 7, 8));
+    }
+
+    #[test]
+    fn empty_sandwich() {
+        // the 1 is at the top of the column, so the 9 has to be right below it
+        let mut data = [ALL; 81];
+        for i in 1..9 {
+            data[i * 9] &= !ONE;
+        }
+        data[0] = ONE;
+        assert!(Sandwich::new(Unit::Col(0), 0).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[9].get_pos(), vec!(9));
+    }
+
+    #[test]
+    fn little_killer_bounds() {
+        let mut data = [ALL; 81];
+        assert!(LittleKiller::new(3, 7, Direction::SouthEast).unwrap().prune(&mut data).is_ok());
+        assert_eq!(data[7].get_pos(), vec!(1, 2));
+        assert_eq!(data[17].get_pos(), vec!(1, 2));
+    }
+}
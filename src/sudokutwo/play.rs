@@ -0,0 +1,286 @@
+use std::io::{self, BufRead, Write};
+
+use crate::sudokutwo::constraint::cell_name;
+use crate::sudokutwo::entry_num::EntryNumThings;
+use crate::sudokutwo::game::{Action, Game};
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+pub const HELP: &str = "Keys: w/a/s/d move, 1-9 fill in, x erase, p<n> toggle pencil mark n, \
+u undo, r redo, ? hint, c check, q quit. Several keys can go on one line.";
+
+/// Something the player asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    Up,
+    Down,
+    Left,
+    Right,
+    Place(u16),
+    Erase,
+    Pencil(u16),
+    Undo,
+    Redo,
+    Hint,
+    Check,
+    Quit,
+}
+
+/// Read the commands on a line of input
+pub fn parse_commands(line: &str) -> Result<Vec<Command>, String> {
+    let mut res = vec!();
+    let mut chars = line.chars().filter(|c| !c.is_whitespace());
+    while let Some(c) = chars.next() {
+        let command = match c {
+            'w' => Command::Up,
+            's' => Command::Down,
+            'a' => Command::Left,
+            'd' => Command::Right,
+            '1'..='9' => Command::Place(c as u16 - '0' as u16),
+            'x' | '0' => Command::Erase,
+            'p' => match chars.next() {
+                Some(n @ '1'..='9') => Command::Pencil(n as u16 - '0' as u16),
+                _ => return Err(String::from("p needs a number from 1 to 9")),
+            },
+            'u' => Command::Undo,
+            'r' => Command::Redo,
+            '?' => Command::Hint,
+            'c' => Command::Check,
+            'q' => Command::Quit,
+            other => return Err(format!("Unknown key '{}'", other)),
+        };
+        res.push(command);
+    }
+    Ok(res)
+}
+
+/// A game being played in the terminal, with a cursor to point at cells
+pub struct Play {
+    game: Game,
+    cursor: usize,
+}
+
+impl Play {
+    pub fn new(game: Game) -> Self {
+        Play { game, cursor: 0 }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Carry out the command
+    /// Returns: what to tell the player
+    pub fn apply(&mut self, command: Command) -> String {
+        let cell = self.cursor;
+        let (row, col) = (cell / 9, cell % 9);
+        let action = match command {
+            Command::Up => {
+                self.cursor = (row + 8) % 9 * 9 + col;
+                return String::new();
+            }
+            Command::Down => {
+                self.cursor = (row + 1) % 9 * 9 + col;
+                return String::new();
+            }
+            Command::Left => {
+                self.cursor = row * 9 + (col + 8) % 9;
+                return String::new();
+            }
+            Command::Right => {
+                self.cursor = row * 9 + (col + 1) % 9;
+                return String::new();
+            }
+            Command::Undo => return if self.game.undo() { String::new() } else { String::from("Nothing to undo") },
+            Command::Redo => return if self.game.redo() { String::new() } else { String::from("Nothing to redo") },
+            Command::Check => return self.check(),
+            Command::Quit => return String::new(),
+            Command::Place(num) => Action::Place { cell, num },
+            Command::Erase => Action::Remove { cell },
+            Command::Pencil(num) => Action::ToggleCandidate { cell, num },
+            Command::Hint => Action::Hint { cell },
+        };
+        match self.game.play(action) {
+            Ok(()) if command == Command::Hint => format!("The solver puts {} at {}",
+                                                         self.game.sudoku().data[cell].get_fixed(), cell_name(cell)),
+            Ok(()) => String::new(),
+            Err(e) => e,
+        }
+    }
+
+    /// Check the board with `is_valid`, and tell the mistakes and time so far
+    fn check(&self) -> String {
+        let sudoku = self.game.sudoku();
+        let verdict = match sudoku.is_valid() {
+            Err(e) => e,
+            Ok(()) if sudoku.count_unfixed() == 0 => String::from("Solved!"),
+            Ok(()) => String::from("No conflicts so far"),
+        };
+        let seconds = self.game.elapsed().as_secs();
+        format!("{} ({} mistakes, {}:{:02})", verdict, self.game.mistakes(), seconds / 60, seconds % 60)
+    }
+
+    /// The grid drawn like the `Display` of `sudoku::Sudoku`: one character per cell and `|` between the stacks.
+    /// A `^` below the row of the cursor points at its cell, and its name and candidates follow on the last line.
+    pub fn render(&self) -> String {
+        let mut res = String::new();
+        for row in 0..9 {
+            for col in 0..9 {
+                let cell = row * 9 + col;
+                if col % 3 == 0 && col > 0 {
+                    res.push('|');
+                }
+                let en = self.game.sudoku().data[cell];
+                res.push(if en.is_fixed() {
+                    (b'0' + en.get_fixed() as u8) as char
+                } else if self.game.candidates(cell) > 0 {
+                    '*'
+                } else {
+                    '.'
+                });
+            }
+            res.push('\n');
+            if row == self.cursor / 9 {
+                let col = self.cursor % 9;
+                res += &format!("{}^\n", " ".repeat(col + col / 3));
+            }
+        }
+        res += &cell_name(self.cursor);
+        if self.game.candidates(self.cursor) > 0 {
+            res += &format!(", pencil marks {:?}", self.game.candidates(self.cursor).get_pos());
+        }
+        res.push('\n');
+        res
+    }
+}
+
+/// Play the puzzle with commands read from the input, drawing the board to the output after every line
+pub fn run<R: BufRead, W: Write>(puzzle: &Sudoku, input: R, mut output: W) -> io::Result<()> {
+    let game = Game::new(puzzle).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut play = Play::new(game);
+    writeln!(output, "{}", HELP)?;
+    write!(output, "{}> ", play.render())?;
+    output.flush()?;
+    for line in input.lines() {
+        let commands = match parse_commands(&line?) {
+            Ok(commands) => commands,
+            Err(e) => {
+                write!(output, "{}\n> ", e)?;
+                output.flush()?;
+                continue;
+            }
+        };
+        let mut messages = vec!();
+        for command in commands {
+            if command == Command::Quit {
+                return Ok(());
+            }
+            let message = play.apply(command);
+            if !message.is_empty() {
+                messages.push(message);
+            }
+        }
+        write!(output, "{}", play.render())?;
+        for message in messages {
+            writeln!(output, "{}", message)?;
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::entry_num::{EntryNumThings, ONE, THREE};
+    use crate::sudokutwo::game::Game;
+    use crate::sudokutwo::origin::Origin;
+    use crate::sudokutwo::play::{Command, parse_commands, Play, run};
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+
+    fn play() -> Play {
+        Play::new(Game::new(&Sudoku::new(String::from(EASY)).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_commands("dd 4 p3"), Ok(vec!(Command::Right, Command::Right, Command::Place(4), Command::Pencil(3))));
+        assert!(parse_commands("p").is_err());
+        assert!(parse_commands("z").is_err());
+    }
+
+    #[test]
+    fn move_and_fill_in() {
+        let mut play = play();
+        play.apply(Command::Up);
+        play.apply(Command::Left);
+        assert_eq!(play.cursor(), 80);
+        play.apply(Command::Right);
+        assert_eq!(play.cursor(), 72);
+        play.apply(Command::Right);
+        play.apply(Command::Right);
+        assert_eq!(play.apply(Command::Place(4)), "r9c3 holds a given");
+        play.apply(Command::Left);
+        assert_eq!(play.apply(Command::Place(5)), "");
+        assert_eq!(play.game().mistakes(), 1);
+        assert_eq!(play.game().sudoku().origin(73), Some(Origin::User));
+        assert!(play.apply(Command::Check).contains("appears more than once"));
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut play = play();
+        play.apply(Command::Pencil(1));
+        play.apply(Command::Pencil(3));
+        assert_eq!(play.game().candidates(0), ONE | THREE);
+        play.apply(Command::Place(4));
+        assert_eq!(play.game().candidates(0), 0);
+        play.apply(Command::Undo);
+        assert_eq!(play.game().sudoku().origin(0), None);
+        assert_eq!(play.game().candidates(0), ONE | THREE);
+        play.apply(Command::Undo);
+        play.apply(Command::Redo);
+        assert_eq!(play.game().candidates(0), ONE | THREE);
+        play.apply(Command::Pencil(1));
+        assert_eq!(play.apply(Command::Redo), "Nothing to redo");
+        assert_eq!(play.game().candidates(0), THREE);
+    }
+
+    #[test]
+    fn hints_solve_the_puzzle() {
+        let mut play = play();
+        assert!(play.apply(Command::Check).starts_with("No conflicts so far (0 mistakes, 0:00"));
+        for _ in 0..81 {
+            play.apply(Command::Hint);
+            play.apply(Command::Right);
+            if play.cursor() % 9 == 0 {
+                play.apply(Command::Down);
+            }
+        }
+        assert!(play.apply(Command::Check).starts_with("Solved!"));
+        assert_eq!(play.game().mistakes(), 0);
+        assert_eq!(play.game().sudoku().data[0].get_fixed(), 4);
+        assert_eq!(play.game().sudoku().givens_line(), EASY);
+    }
+
+    #[test]
+    fn render_in_the_terminal() {
+        let mut out = vec!();
+        run(&Sudoku::new(String::from(EASY)).unwrap(), "dd\n?p\nd p5\nq\n4\n".as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("9..|3.5|..1\n"));
+        assert!(out.contains("..3|.2.|6..\n  ^\n"));
+        assert!(out.contains("p needs a number"));
+        assert!(out.contains("..3|*2.|6..\n    ^\n9..|3.5|..1\n"));
+        assert!(out.contains("r1c4, pencil marks [5]\n"));
+        // drawn at the start and after every line until the player quits, but not for the line that can't be read
+        assert_eq!(out.matches("..8|1.2|9..\n").count(), 3);
+    }
+}
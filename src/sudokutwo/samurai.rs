@@ -0,0 +1,235 @@
+use std::convert::TryInto;
+use std::fmt::{Display, Error, Formatter};
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::entry_num::{self, EntryNumThings};
+use crate::sudokutwo::origin::Origin;
+use crate::sudokutwo::solver_config::SolverConfig;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+/// Width and height of the full samurai layout
+pub const SIZE: usize = 21;
+
+/// Where the top left cell of each grid sits in the 21x21 layout:
+/// top left, top right, middle, bottom left, bottom right
+pub const OFFSETS: [(usize, usize); 5] = [(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)];
+
+/// Five sudokus that overlap at their corner blocks: the middle grid shares each of its corner blocks
+/// with the inner corner block of one of the other grids
+#[derive(Clone)]
+pub struct Samurai {
+    grids: [Sudoku; 5],
+}
+
+/// The grids (and cell index within them) covering a position of the 21x21 layout
+fn grids_at(row: usize, col: usize) -> Vec<(usize, usize)> {
+    OFFSETS.iter().enumerate()
+        .filter(|(_, (r, c))| (*r..r + 9).contains(&row) && (*c..c + 9).contains(&col))
+        .map(|(g, (r, c))| (g, (row - r) * 9 + col - c))
+        .collect()
+}
+
+/// Every pair of cells that are the same cell of the layout, as (grid, index) pairs
+fn shared_cells() -> Vec<((usize, usize), (usize, usize))> {
+    let mut res = vec!();
+    for row in 0..SIZE {
+        for col in 0..SIZE {
+            if let [a, b] = grids_at(row, col)[..] {
+                res.push((a, b));
+            }
+        }
+    }
+    res
+}
+
+impl Samurai {
+    /// Combine five grids, in the order of `OFFSETS`, into a samurai
+    pub fn new(grids: [Sudoku; 5]) -> Result<Self, String> {
+        for ((ga, a), (gb, b)) in shared_cells() {
+            let (x, y) = (grids[ga].data[a], grids[gb].data[b]);
+            if x.is_fixed() && y.is_fixed() && x != y {
+                return Err(format!("Grids {} and {} disagree on a shared cell: {} and {}",
+                                   ga, gb, x.get_fixed(), y.get_fixed()));
+            }
+        }
+        Ok(Samurai { grids })
+    }
+
+    /// Parse the 21x21 layout: 21 lines of 21 characters, with a digit or 0/. for every cell of a grid.
+    /// Characters outside of the grids are ignored, and lines may stop early when the rest is outside.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let lines = text.trim_end().lines().map(|l| l.chars().collect::<Vec<_>>()).collect::<Vec<_>>();
+        if lines.len() != SIZE {
+            return Err(format!("A samurai needs {} lines, found {}", SIZE, lines.len()));
+        }
+        let mut grid_lines = vec![vec!['0'; 81]; 5];
+        for (row, line) in lines.iter().enumerate() {
+            for col in 0..SIZE {
+                for (g, i) in grids_at(row, col) {
+                    grid_lines[g][i] = match line.get(col) {
+                        Some('.') => '0',
+                        Some(c) => *c,
+                        None => return Err(format!("Line {} of the samurai stops at column {}", row + 1, col + 1)),
+                    };
+                }
+            }
+        }
+        let mut grids = vec!();
+        for line in grid_lines {
+            grids.push(Sudoku::new(line.into_iter().collect())?);
+        }
+        let grids: [Sudoku; 5] = grids.try_into().map_err(|_| String::from("A samurai needs five grids"))?;
+        Samurai::new(grids)
+    }
+
+    /// The grids, in the order of `OFFSETS`
+    pub fn grids(&self) -> &[Sudoku; 5] {
+        &self.grids
+    }
+
+    /// Make the possibilities of every shared cell the same in both grids
+    /// Returns: the number of possibilities crossed out
+    fn sync_shared(&mut self) -> usize {
+        let mut res = 0;
+        for ((ga, a), (gb, b)) in shared_cells() {
+            let both = self.grids[ga].data[a] & self.grids[gb].data[b];
+            res += Sudoku::remove_possibilities(&mut self.grids[ga].data, a, !both) as usize;
+            res += Sudoku::remove_possibilities(&mut self.grids[gb].data, b, !both) as usize;
+        }
+        res
+    }
+
+    /// Make a single step with the config in every grid, then share what was found between the grids
+    /// Returns: the number of moves made
+    pub fn solve_once_with(&mut self, config: &SolverConfig) -> usize {
+        let res: usize = self.grids.iter_mut().map(|g| g.solve_once_with(config)).sum();
+        res + self.sync_shared()
+    }
+
+    pub fn solve_once(&mut self) -> usize {
+        self.solve_once_with(&SolverConfig::default())
+    }
+
+    /// Check that every grid is still valid
+    pub fn is_valid(&self) -> Result<(), String> {
+        for (g, grid) in self.grids.iter().enumerate() {
+            grid.is_valid().map_err(|msg| format!("Grid {}: {}", g, msg))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.grids.iter().all(|g| g.is_solved())
+    }
+
+    /// Attempt to solve all the grids together, guessing when the techniques get stuck
+    /// Returns true if successfully solved, false otherwise
+    pub fn attempt_solve(&mut self) -> bool {
+        match self.search(&SolverConfig::default()) {
+            Some(solved) => {
+                *self = solved;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Depth first search over the guesses, kept on the heap: a samurai can need a few hundred guesses
+    /// in a row, which is too deep to recurse into with five grids per step
+    fn search(&self, config: &SolverConfig) -> Option<Samurai> {
+        let mut to_visit = vec!(self.clone());
+        while let Some(mut s) = to_visit.pop() {
+            while s.solve_once_with(config) > 0 {}
+            if s.is_valid().is_err() {
+                continue;
+            }
+            if s.is_solved() {
+                return Some(s);
+            }
+            let guess_at = s.grids.iter().enumerate()
+                .flat_map(|(g, grid)| grid.data.iter().enumerate().map(move |(i, en)| (g, i, *en)))
+                .filter(|(_, _, en)| !en.is_fixed())
+                .min_by_key(|(_, _, en)| en.count_ones());
+            if let Some((g, index, en)) = guess_at {
+                // pushed in reverse, so the smallest number is tried first
+                for num in en.get_pos().into_iter().rev() {
+                    let mut guess = s.clone();
+                    guess.grids[g].fill(index, entry_num::to_entry_num(num), Origin::Guess);
+                    to_visit.push(guess);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Display for Samurai {
+    /// Writes the 21x21 layout in the format `parse` reads, with spaces outside the grids
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for row in 0..SIZE {
+            let line = (0..SIZE)
+                .map(|col| match grids_at(row, col).first() {
+                    Some((g, i)) if self.grids[*g].data[*i].is_fixed() =>
+                        (b'0' + self.grids[*g].data[*i].get_fixed() as u8) as char,
+                    Some(_) => '0',
+                    None => ' ',
+                })
+                .collect::<String>();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::samurai::{grids_at, Samurai, shared_cells, SIZE};
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    fn empty_layout() -> String {
+        (0..SIZE)
+            .map(|row| (0..SIZE).map(|col| if grids_at(row, col).is_empty() { ' ' } else { '0' }).collect::<String>())
+            .map(|line| line.trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn overlap() {
+        // four corner blocks of 9 cells each
+        assert_eq!(shared_cells().len(), 36);
+        assert_eq!(grids_at(7, 7), vec!((0, 70), (2, 10)));
+        assert_eq!(grids_at(9, 0), vec!());
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let text = empty_layout().replacen('0', "5", 1);
+        let samurai = Samurai::parse(&text).unwrap();
+        assert_eq!(samurai.to_string().trim_end(), text);
+        assert!(Samurai::parse("000").is_err());
+    }
+
+    #[test]
+    fn solve_together() {
+        let mut samurai = Samurai::parse(&empty_layout()).unwrap();
+        assert!(samurai.attempt_solve());
+        for ((ga, a), (gb, b)) in shared_cells() {
+            assert_eq!(samurai.grids()[ga].data[a], samurai.grids()[gb].data[b]);
+        }
+        // take the solution, empty the middle grid apart from its corners, and solve again
+        let solved = samurai.to_string();
+        let puzzle = solved.lines().enumerate()
+            .map(|(row, line)| line.chars().enumerate()
+                .map(|(col, c)| match grids_at(row, col)[..] {
+                    [(2, _)] => '0',
+                    _ => c,
+                })
+                .collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut samurai = Samurai::parse(&puzzle).unwrap();
+        assert!(samurai.attempt_solve());
+        assert!(samurai.grids().iter().all(|g| g.is_solved()));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::technique::{HiddenSingles, NakedSingles, NakedSubsets, Omissions, Technique, VariantConstraints};
+
+/// Which techniques the solver uses, and in what order
+pub struct SolverConfig {
+    techniques: Vec<Box<dyn Technique>>,
+    restart_on_progress: bool,
+}
+
+impl SolverConfig {
+    /// Create a config that applies the given techniques in order.
+    /// When `restart_on_progress` is set, a step stops at the first technique that makes progress,
+    /// so the next step starts again with the simplest technique.
+    pub fn new(techniques: Vec<Box<dyn Technique>>, restart_on_progress: bool) -> Self {
+        SolverConfig { techniques, restart_on_progress }
+    }
+
+    /// Only naked and hidden singles (and the variant rules), for puzzles that should be solvable without pencil marks
+    pub fn singles_only() -> Self {
+        SolverConfig::new(vec!(Box::new(NakedSingles), Box::new(VariantConstraints), Box::new(HiddenSingles)), true)
+    }
+
+    /// Every technique, always falling back to the simplest one that still makes progress
+    pub fn expert() -> Self {
+        SolverConfig::new(vec!(
+            Box::new(NakedSingles),
+            Box::new(VariantConstraints),
+            Box::new(HiddenSingles),
+            Box::new(NakedSubsets),
+            Box::new(Omissions),
+        ), true)
+    }
+
+    /// Every technique, each applied once per step, so every step does a comparable amount of work
+    pub fn benchmark() -> Self {
+        SolverConfig::new(vec!(
+            Box::new(NakedSingles),
+            Box::new(VariantConstraints),
+            Box::new(HiddenSingles),
+            Box::new(NakedSubsets),
+            Box::new(Omissions),
+        ), false)
+    }
+
+    /// Add a technique to the end of the pipeline
+    pub fn push(&mut self, technique: Box<dyn Technique>) {
+        self.techniques.push(technique);
+    }
+
+    pub fn techniques(&self) -> &[Box<dyn Technique>] {
+        &self.techniques
+    }
+
+    pub fn restarts_on_progress(&self) -> bool {
+        self.restart_on_progress
+    }
+
+    /// Make a single step through the enabled techniques
+    /// Returns: the number of possibilities crossed out
+    pub fn step(&self, sudoku: &mut Sudoku) -> usize {
+        let mut res = 0;
+        for technique in &self.techniques {
+            res += technique.apply(sudoku) as usize;
+            if res > 0 && self.restart_on_progress {
+                break;
+            }
+        }
+        res
+    }
+
+    /// Keep stepping until no technique makes any more progress
+    /// Returns: the total number of possibilities crossed out
+    pub fn run(&self, sudoku: &mut Sudoku) -> usize {
+        let mut res = 0;
+        loop {
+            let progress = self.step(sudoku);
+            if progress == 0 {
+                return res;
+            }
+            res += progress;
+        }
+    }
+}
+
+impl Default for SolverConfig {
+    /// The pipeline `solve_once` has always used: subsets, hidden singles, then omissions,
+    /// with the variant rules pruned right after the subsets
+    fn default() -> Self {
+        SolverConfig::new(vec!(
+            Box::new(NakedSubsets),
+            Box::new(VariantConstraints),
+            Box::new(HiddenSingles),
+            Box::new(Omissions),
+        ), false)
+    }
+}
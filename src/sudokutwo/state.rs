@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::constraint::cell_name;
+use crate::sudokutwo::description::Description;
+use crate::sudokutwo::entry_num::{self, EntryNum, EntryNumThings};
+use crate::sudokutwo::origin::Origin;
+use crate::sudokutwo::Sudoku;
+
+/// A sudoku being solved, in JSON for other services:
+/// ```json
+/// {
+///   "puzzle": {"givens": "003020600...", "thermometers": [["r1c1", "r1c2", "r1c3"]]},
+///   "candidates": ["4", "58", "3", ...],
+///   "origins": ["user", null, "given", ...],
+///   "metadata": {"source": "euler", "title": "Grid 01"}
+/// }
+/// ```
+/// The puzzle is a `Description`, so it carries the layout and variant rules along.
+/// Candidates hold the numbers still possible in each cell, a single number for a filled in cell.
+/// Origins tell where the number of each filled in cell came from, and are null for the other cells;
+/// without them, filled in cells that aren't givens count as deduced. Metadata is free to fill in.
+/// Origins and metadata can be left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SudokuState {
+    pub puzzle: Description,
+    pub candidates: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origins: Vec<Option<Origin>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// The numbers of the entry as digits, like "158"
+fn candidates_to_string(en: EntryNum) -> String {
+    en.get_pos().iter().map(|num| (b'0' + *num as u8) as char).collect()
+}
+
+/// Read the candidates of a cell, which need at least one number and no number twice
+fn candidates_from_string(cell: usize, s: &str) -> Result<EntryNum, String> {
+    if s.is_empty() {
+        return Err(format!("{} has no candidates", cell_name(cell)));
+    }
+    s.chars().try_fold(0, |res, c| match c {
+        '1'..='9' => {
+            let en = entry_num::to_entry_num(c as u16 - '0' as u16);
+            if res & en > 0 {
+                return Err(format!("The candidates of {} have {} twice", cell_name(cell), c));
+            }
+            Ok(res | en)
+        }
+        _ => Err(format!("The candidates of {} can't hold '{}'", cell_name(cell), c)),
+    })
+}
+
+impl SudokuState {
+    /// The state of a sudoku created from the puzzle with `Description::to_sudoku`
+    pub fn new(puzzle: Description, sudoku: &Sudoku) -> Self {
+        SudokuState {
+            puzzle,
+            candidates: sudoku.data.iter().map(|en| candidates_to_string(*en)).collect(),
+            origins: (0..81).map(|i| sudoku.origin(i)).collect(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    /// The state of a sudoku, describing its puzzle with `Description::from_sudoku`
+    /// Returns: an error if the layout or one of the rules can't be written down
+    pub fn from_sudoku(sudoku: &Sudoku) -> Result<Self, String> {
+        Ok(SudokuState::new(Description::from_sudoku(sudoku)?, sudoku))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid sudoku state: {}", e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a sudoku state always converts to JSON")
+    }
+
+    /// Create the sudoku with its layout and variant rules, cross out everything but the candidates,
+    /// and mark where the numbers came from
+    /// Returns: an error if the puzzle is invalid, the candidates can't be read or drop a given,
+    /// or the origins don't match the givens and candidates
+    pub fn to_sudoku(&self) -> Result<Sudoku, String> {
+        let mut sudoku = self.puzzle.to_sudoku()?;
+        if self.candidates.len() != 81 {
+            return Err(format!("Expected candidates for 81 cells, got {}", self.candidates.len()));
+        }
+        if !self.origins.is_empty() && self.origins.len() != 81 {
+            return Err(format!("Expected origins for 81 cells, got {}", self.origins.len()));
+        }
+        for (cell, candidates) in self.candidates.iter().enumerate() {
+            let en = candidates_from_string(cell, candidates)?;
+            let given = sudoku.origin(cell) == Some(Origin::Given);
+            if given && en != sudoku.data[cell] {
+                return Err(format!("{} holds the given {}, but its candidates are '{}'",
+                                   cell_name(cell), sudoku.data[cell].get_fixed(), candidates));
+            }
+            let origin = match self.origins.get(cell) {
+                None if given => Some(Origin::Given),
+                None if en.is_fixed() => Some(Origin::Logic),
+                None => None,
+                Some(origin) => *origin,
+            };
+            match origin {
+                Some(Origin::Given) if given => {}
+                _ if given => return Err(format!("{} holds a given, but its origin isn't \"given\"", cell_name(cell))),
+                Some(Origin::Given) => return Err(format!("{} is not a given of the puzzle", cell_name(cell))),
+                Some(origin) if en.is_fixed() => sudoku.fill(cell, en, origin),
+                None if !en.is_fixed() => sudoku.data[cell] = en,
+                _ => return Err(format!("The origin of {} doesn't match its candidates '{}'", cell_name(cell), candidates)),
+            }
+        }
+        Ok(sudoku)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::sudokutwo::anti_chess::AntiMove;
+    use crate::sudokutwo::description::{Description, LayoutDescription};
+    use crate::sudokutwo::entry_num::{FIVE, ONE, THREE, TWO};
+    use crate::sudokutwo::killer::{Cage, KillerSudoku};
+    use crate::sudokutwo::layout::Layout;
+    use crate::sudokutwo::lines::Thermometer;
+    use crate::sudokutwo::origin::Origin;
+    use crate::sudokutwo::state::SudokuState;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+
+    #[test]
+    fn candidates_round_trip() {
+        let mut s = Sudoku::new(String::from(EASY)).unwrap();
+        s.data[0] = ONE | FIVE;
+        s.place(1, 8).unwrap();
+        let mut state = SudokuState::from_sudoku(&s).unwrap();
+        state.metadata.insert(String::from("title"), String::from("easy"));
+        let json = state.to_json();
+        assert!(json.starts_with(&format!(r#"{{"puzzle":{{"givens":"{}"}},"candidates":["15","8","3","123456789""#, EASY)), "{}", json);
+        assert!(json.contains(r#""origins":[null,"user","given",null"#), "{}", json);
+        assert!(json.ends_with(r#""metadata":{"title":"easy"}}"#));
+        let back = SudokuState::from_json(&json).unwrap();
+        assert_eq!(back, state);
+        let sudoku = back.to_sudoku().unwrap();
+        assert_eq!(sudoku.data, s.data);
+        assert_eq!(sudoku.origin(1), Some(Origin::User));
+        assert_eq!(sudoku.givens_line(), EASY);
+
+        // without origins, filled in cells count as deduced
+        let mut state = SudokuState::from_sudoku(&s).unwrap();
+        state.origins.clear();
+        assert_eq!(state.to_sudoku().unwrap().origin(1), Some(Origin::Logic));
+    }
+
+    #[test]
+    fn variant_rules() {
+        let mut puzzle = Description::new("0".repeat(81));
+        puzzle.thermometers.push(Thermometer::new(vec!(0, 1, 2)).unwrap());
+        let mut s = puzzle.to_sudoku().unwrap();
+        s.data[0] = THREE;
+        let state = SudokuState::from_sudoku(&s).unwrap();
+        assert_eq!(state, SudokuState::new(puzzle, &s));
+        assert!(state.to_json().contains(r#""thermometers":[["r1c1","r1c2","r1c3"]]"#));
+        let sudoku = SudokuState::from_json(&state.to_json()).unwrap().to_sudoku().unwrap();
+        assert_eq!(sudoku.constraints.len(), 1);
+        assert_eq!(sudoku.data[0], THREE);
+    }
+
+    #[test]
+    fn killer_windoku() {
+        let killer = KillerSudoku::new("0".repeat(81), vec!("3=r1c1 r1c2".parse::<Cage>().unwrap())).unwrap();
+        let mut s = killer.sudoku().clone();
+        s.layout = Layout::windoku();
+        s.add_constraint(Arc::new(AntiMove::king()));
+        let json = SudokuState::from_sudoku(&s).unwrap().to_json();
+        assert!(json.contains(r#""layout":"windoku","cages":["3=r1c1 r1c2"],"anti_chess":["king"]"#), "{}", json);
+        let back = SudokuState::from_json(&json).unwrap();
+        assert_eq!(back.puzzle.layout, LayoutDescription::Windoku);
+        let mut sudoku = back.to_sudoku().unwrap();
+        // the cage, the 45 rule and the anti-king rule
+        assert_eq!(sudoku.constraints.len(), 3);
+        assert!(sudoku.attempt_solve());
+        assert_eq!(sudoku.data[0] | sudoku.data[1], ONE | TWO);
+    }
+
+    #[test]
+    fn killer_jigsaw() {
+        // the rows are the regions, so the 45 rule may not use the 3x3 blocks
+        let solution: String = (0..81).map(|i| (b'1' + ((i / 9 + i % 9) % 9) as u8) as char).collect();
+        let mut puzzle = Description::new(format!("00{}", &solution[2..]));
+        puzzle.layout = LayoutDescription::Jigsaw((0..81).map(|i| (b'A' + (i / 9) as u8) as char).collect());
+        puzzle.cages.push("3=r1c1 r1c2".parse::<Cage>().unwrap());
+        let json = SudokuState::from_sudoku(&puzzle.to_sudoku().unwrap()).unwrap().to_json();
+        let mut sudoku = SudokuState::from_json(&json).unwrap().to_sudoku().unwrap();
+        assert_eq!(sudoku.is_valid(), Ok(()));
+        assert!(sudoku.attempt_solve());
+        assert_eq!(sudoku.to_line(), solution);
+    }
+
+    #[test]
+    fn invalid_states() {
+        let state = |candidates: &str| format!(r#"{{"puzzle": {{"givens": "{}"}}, "candidates": [{}]}}"#, EASY, candidates);
+        let error = |json: &str| SudokuState::from_json(json).unwrap().to_sudoku().err();
+        let all = vec!(r#""123456789""#; 81).join(",");
+        assert_eq!(error(&state(&all)), Some(String::from("r1c3 holds the given 3, but its candidates are '123456789'")));
+        assert!(error(&state(r#""1""#)).is_some());
+        for (first, message) in [(r#""x""#, "The candidates of r1c1 can't hold 'x'"),
+                                 (r#""""#, "r1c1 has no candidates"),
+                                 (r#""141""#, "The candidates of r1c1 have 1 twice")].iter() {
+            let candidates = format!("{},{}", first, vec!(r#""1""#; 80).join(","));
+            assert_eq!(error(&state(&candidates)).as_deref(), Some(*message));
+        }
+
+        let mut s = SudokuState::from_sudoku(&Sudoku::new(String::from(EASY)).unwrap()).unwrap();
+        s.origins[0] = Some(Origin::User);
+        assert_eq!(s.to_sudoku().err(), Some(String::from("The origin of r1c1 doesn't match its candidates '123456789'")));
+        s.origins[0] = Some(Origin::Given);
+        assert_eq!(s.to_sudoku().err(), Some(String::from("r1c1 is not a given of the puzzle")));
+        s.origins[0] = None;
+        s.origins[2] = Some(Origin::User);
+        assert_eq!(s.to_sudoku().err(), Some(String::from(r#"r1c3 holds a given, but its origin isn't "given""#)));
+        assert!(SudokuState::from_json(r#"{"puzzle": {"givens": "0"}, "candidates": [], "notes": ""}"#).is_err());
+    }
+}
@@ -1,24 +1,36 @@
 use crate::sudokutwo::{entry_num, Sudoku};
-use crate::sudokutwo::entry_num::ALL;
+use crate::sudokutwo::constraint::cell_name;
+use crate::sudokutwo::entry_num::{ALL, EntryNumThings};
+use crate::sudokutwo::layout::Layout;
+use crate::sudokutwo::origin::Origin;
+use crate::sudokutwo::solver_config::SolverConfig;
 
 pub trait SudokuApi {
     /// Create a new sudoku puzzle entity from a line of text
     fn new(line: String) -> Result<Self, String> where Self: Sized;
 
-    /// Attempt to solve the Sudoku
+    /// Write the puzzle back as a line of text, with a 0 for every cell that is not fixed yet
+    fn to_line(&self) -> String;
+
+    /// Attempt to solve the Sudoku, guessing when the techniques get stuck
     /// Returns true if successfully solved, false otherwise
     fn attempt_solve(&mut self) -> bool;
 
     /// Go through the puzzle once and make as many moves as possible, a move being defined as
     /// 1. Filling in an Entry
     /// 2. Crossing out a possible number
+    ///
     /// Returns: the number of moves made
     fn solve_once(&mut self) -> usize;
 
+    /// Same as `solve_once`, but only using the techniques enabled in the given config
+    fn solve_once_with(&mut self, config: &SolverConfig) -> usize;
+
     /// Count the number of non-fixed numbers
     fn count_unfixed(&self) -> usize;
 
     /// Check that the puzzle is still valid
+    /// Returns: the first problem found, see `Sudoku::validate` for all of them
     fn is_valid(&self) -> Result<(), String>;
 
     /// Returns true iff. the puzzle is valid & has all numbers filled in
@@ -31,8 +43,9 @@ impl SudokuApi for Sudoku {
             return Err(format!("Line \'{}\' needs to be 81 characters long", line));
         }
         let mut data: [u16; 81] = [0; 81];
+        let mut origins = [Origin::Logic; 81];
         for (i, c) in line.chars().enumerate() {
-            if !c.is_numeric() {
+            if !c.is_ascii_digit() {
                 return Err(format!(
                     "Line \'{}\' contains non-numeric character '{}' at index {}",
                     line, c, i
@@ -42,25 +55,39 @@ impl SudokuApi for Sudoku {
                 let fixed = c as u16 - '0' as u16;
                 let entried = entry_num::to_entry_num(fixed);
                 data[i] = entried;
+                origins[i] = Origin::Given;
             } else {
                 data[i] = ALL;
             }
         }
-        Ok(Self { data })
+        Ok(Self { data, layout: Layout::standard(), constraints: vec!(), origins })
     }
 
 
+    fn to_line(&self) -> String {
+        self.data.iter()
+            .map(|en| if en.is_fixed() { (b'0' + en.get_fixed() as u8) as char } else { '0' })
+            .collect()
+    }
+
     fn attempt_solve(&mut self) -> bool {
-        false
+        match self.search(&SolverConfig::default()) {
+            Some(solved) => {
+                *self = solved;
+                true
+            }
+            None => false,
+        }
     }
 
     fn solve_once(&mut self) -> usize {
-        // eliminate the numbers based on sets of numbers (of size 1 or more)
-        let res = Sudoku::eliminate_basic_possibilities(self);
-        //todo forced fills
-        let res = res + Sudoku::eliminate_omissions(self);
-        res as usize
+        self.solve_once_with(&SolverConfig::default())
     }
+
+    fn solve_once_with(&mut self, config: &SolverConfig) -> usize {
+        config.step(self)
+    }
+
     fn count_unfixed(&self) -> usize {
         self.data.iter().filter(|en| (**en).count_ones() > 1).count()
     }
@@ -68,35 +95,14 @@ impl SudokuApi for Sudoku {
     fn is_valid(&self) -> Result<(), String> {
         // check that the puzzle is still valid
         // simple check => no EntryNum that is 0
-        if self.data.iter().any(|x| *x == 0) {
-            return Err(format!("At position {}, there are no possibilities left", self.data.iter().find(|x| **x == 0).unwrap()));
-        }
-        // for each row, column, and box, assert that each number is still possible
-        for i in 0..9 {
-            let row_nums = (0..9).map(|x| i * 9 + x).collect::<Vec<_>>();
-            let col_nums = (0..9).map(|x| x * 9 + i).collect::<Vec<_>>();
-            let box_row = i / 3;
-            let box_col = i % 3;
-            let box_nums = (0..3).flat_map(|b_r|
-                (0..3).map(|b_c| (box_row * 3 + b_r) * 9 + box_col * 3 + b_c).collect::<Vec<_>>())
-                .collect::<Vec<_>>();
-            for num in 1..=9 {
-                let mask = entry_num::to_entry_num(num);
-                if !row_nums.iter().any(|x| self.data[*x] & mask > 0) {
-                    return Err(format!("{} is not possible in row {}", num, i));
-                }
-                if !col_nums.iter().any(|x| self.data[*x] & mask > 0) {
-                    return Err(format!("{} is not possible in column {}", num, i));
-                }
-                if !box_nums.iter().any(|x| self.data[*x] & mask > 0) {
-                    return Err(format!("{} is not possible in box {}", num, i));
-                }
-            }
+        if let Some(cell) = self.data.iter().position(|x| *x == 0) {
+            return Err(format!("At {}, there are no possibilities left", cell_name(cell)));
         }
-        Ok(())
+        // every unit (row, column, box, ...) and every variant rule can still be met
+        self.check_constraints()
     }
     fn is_solved(&self) -> bool {
-        return self.is_valid().is_ok() && self.data.iter().all(|x| (*x).count_ones() == 1);
+        self.is_valid().is_ok() && self.data.iter().all(|x| (*x).count_ones() == 1)
     }
 }
 
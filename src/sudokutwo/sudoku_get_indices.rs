@@ -1,35 +1,80 @@
-use crate::sudokutwo::Sudoku;
-
-impl Sudoku {
-    /// Get all the indices of the given block (at the given block index)
-    pub(crate) fn get_block_indices_by_cell_index(cell_index: usize) -> Vec<usize> {
-        let box_col = (cell_index % 9) / 3;
-        let box_row = cell_index / 27;
-        return Sudoku::get_block_indices(box_row, box_col);
+/// All 27 units of the puzzle: the rows (0-8), then the columns (9-17), then the blocks (18-26)
+pub(crate) const UNITS: [[usize; 9]; 27] = build_units();
+
+/// For each cell, the index in `UNITS` of its row, column and block
+pub(crate) const CELL_UNITS: [[usize; 3]; 81] = build_cell_units();
+
+/// For each cell, the 20 other cells that share a row, column or block with it
+pub(crate) const PEERS: [[usize; 20]; 81] = build_peers();
+
+const fn build_units() -> [[usize; 9]; 27] {
+    let mut units = [[0; 9]; 27];
+    let mut i = 0;
+    while i < 9 {
+        let mut x = 0;
+        while x < 9 {
+            units[i][x] = i * 9 + x;
+            units[9 + i][x] = x * 9 + i;
+            units[18 + i][x] = ((i / 3) * 3 + x / 3) * 9 + (i % 3) * 3 + x % 3;
+            x += 1;
+        }
+        i += 1;
     }
+    units
+}
 
-    pub(crate) fn get_block_index(cell_index: usize) -> usize {
-        let box_col = (cell_index % 9) / 3;
-        let box_row = cell_index / 27;
-        box_row * 3 + box_col
+const fn build_cell_units() -> [[usize; 3]; 81] {
+    let mut cell_units = [[0; 3]; 81];
+    let mut cell = 0;
+    while cell < 81 {
+        cell_units[cell] = [cell / 9, 9 + cell % 9, 18 + (cell / 27) * 3 + (cell % 9) / 3];
+        cell += 1;
     }
+    cell_units
+}
 
-    pub(crate) fn get_block_indices(block_row: usize, block_col: usize) -> Vec<usize> {
-        (0..3).flat_map(
-            |b_r| (0..3).map(|b_c| (block_row * 3 + b_r) * 9 + (block_col * 3) + b_c).collect::<Vec<_>>()).collect::<Vec<_>>()
+const fn build_peers() -> [[usize; 20]; 81] {
+    let cell_units = build_cell_units();
+    let mut peers = [[0; 20]; 81];
+    let mut cell = 0;
+    while cell < 81 {
+        let mut count = 0;
+        let mut other = 0;
+        while other < 81 {
+            let [row, col, block] = cell_units[other];
+            if other != cell && (row == cell_units[cell][0] || col == cell_units[cell][1] || block == cell_units[cell][2]) {
+                peers[cell][count] = other;
+                count += 1;
+            }
+            other += 1;
+        }
+        cell += 1;
     }
+    peers
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::sudoku_get_indices::{CELL_UNITS, PEERS, UNITS};
 
-    pub(crate) fn get_col_indices(cell_index: usize) -> Vec<usize> {
-        (0..9)
-            .map(|x| x * 9 + (cell_index % 9))
-            //                    .filter(|x| x != &index)
-            .collect::<Vec<_>>()
+    #[test]
+    fn every_cell_in_three_units() {
+        for (cell, units) in CELL_UNITS.iter().enumerate() {
+            let containing = (0..27).filter(|u| UNITS[*u].contains(&cell)).collect::<Vec<_>>();
+            assert_eq!(containing, units.to_vec());
+        }
     }
 
-    pub(crate) fn get_row_indices(cell_index: usize) -> Vec<usize> {
-        (0..9)
-            .map(|x| (cell_index / 9) * 9 + x)
-            //                    .filter(|x| x != &index)
-            .collect::<Vec<_>>()
+    #[test]
+    fn peers_share_a_unit() {
+        for (cell, units) in CELL_UNITS.iter().enumerate() {
+            let mut expected = units.iter()
+                .flat_map(|u| UNITS[*u].iter().copied())
+                .filter(|other| *other != cell)
+                .collect::<Vec<_>>();
+            expected.sort_unstable();
+            expected.dedup();
+            assert_eq!(PEERS[cell].to_vec(), expected);
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,56 @@
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::entry_num::{EntryNumThings, NUMS};
+use crate::sudokutwo::origin::Origin;
+use crate::sudokutwo::solver_config::SolverConfig;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+impl Sudoku {
+    /// Solve the puzzle by applying the techniques in the config, and guessing when they get stuck.
+    /// Returns: the first solution found, or None when the puzzle has no solution
+    pub(crate) fn search(&self, config: &SolverConfig) -> Option<Sudoku> {
+        let mut solutions = vec!();
+        self.search_solutions(config, 1, &mut solutions);
+        solutions.pop()
+    }
+
+    /// Count the solutions of the puzzle, stopping as soon as `limit` have been found.
+    /// A puzzle with a unique solution gives 1 with a limit of 2.
+    /// Returns: the number of solutions found, at most `limit`
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        let mut solutions = vec!();
+        self.search_solutions(&SolverConfig::default(), limit, &mut solutions);
+        solutions.len()
+    }
+
+    /// Apply the techniques in the config, and guess when they get stuck, until `limit` solutions are found.
+    /// Guesses are made in the cell with the fewest possibilities left, and marked with `Origin::Guess`.
+    fn search_solutions(&self, config: &SolverConfig, limit: usize, solutions: &mut Vec<Sudoku>) {
+        if solutions.len() >= limit {
+            return;
+        }
+        let mut s = self.clone();
+        config.run(&mut s);
+        if s.is_valid().is_err() {
+            return;
+        }
+        // already known to be valid, so every cell being fixed means it is solved
+        if s.count_unfixed() == 0 {
+            solutions.push(s);
+            return;
+        }
+        let (index, en) = match s.data.iter().copied().enumerate()
+            .filter(|(_, en)| !en.is_fixed())
+            .min_by_key(|(_, en)| en.count_ones()) {
+            Some(guess_at) => guess_at,
+            None => return,
+        };
+        for num in NUMS.iter().filter(|num| en & **num > 0) {
+            let mut guess = s.clone();
+            guess.fill(index, *num, Origin::Guess);
+            guess.search_solutions(config, limit, solutions);
+            if solutions.len() >= limit {
+                return;
+            }
+        }
+    }
+}
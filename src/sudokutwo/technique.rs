@@ -0,0 +1,108 @@
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::trace::Difficulty;
+
+/// A single number crossed out of a single cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Elimination {
+    pub cell: usize,
+    pub num: u16,
+}
+
+/// A single solving technique that can be switched on or off in a `SolverConfig`
+pub trait Technique {
+    /// Short name of the technique, used when reporting what the solver did
+    fn name(&self) -> &'static str;
+
+    /// How hard the technique is for a person, used when rating puzzles
+    fn difficulty(&self) -> Difficulty;
+
+    /// Apply the technique to the whole puzzle once
+    /// Returns: the number of possibilities that were crossed out
+    fn apply(&self, sudoku: &mut Sudoku) -> u32;
+}
+
+/// Cross out numbers that are already fixed in the same row, column or block
+pub struct NakedSingles;
+
+/// Fill in a number that has only one place left in a row, column or block
+pub struct HiddenSingles;
+
+/// Cross out numbers that are taken by sets of cells (naked pairs, triples, ...) in the same row,
+/// column or block, including the fixed numbers
+pub struct NakedSubsets;
+
+/// Pointing pairs/triples and box/line reduction
+pub struct Omissions;
+
+/// Cross out whatever the variant rules added to the puzzle (cages, ...) rule out
+pub struct VariantConstraints;
+
+impl Technique for NakedSingles {
+    fn name(&self) -> &'static str {
+        "naked singles"
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        Difficulty::Easy
+    }
+
+    fn apply(&self, sudoku: &mut Sudoku) -> u32 {
+        sudoku.eliminate_fixed_peers()
+    }
+}
+
+impl Technique for HiddenSingles {
+    fn name(&self) -> &'static str {
+        "hidden singles"
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        Difficulty::Easy
+    }
+
+    fn apply(&self, sudoku: &mut Sudoku) -> u32 {
+        sudoku.fill_hidden_singles()
+    }
+}
+
+impl Technique for NakedSubsets {
+    fn name(&self) -> &'static str {
+        "naked subsets"
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        Difficulty::Medium
+    }
+
+    fn apply(&self, sudoku: &mut Sudoku) -> u32 {
+        sudoku.eliminate_basic_possibilities()
+    }
+}
+
+impl Technique for Omissions {
+    fn name(&self) -> &'static str {
+        "omissions"
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        Difficulty::Hard
+    }
+
+    fn apply(&self, sudoku: &mut Sudoku) -> u32 {
+        sudoku.eliminate_omissions()
+    }
+}
+
+impl Technique for VariantConstraints {
+    fn name(&self) -> &'static str {
+        "variant constraints"
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        Difficulty::Easy
+    }
+
+    fn apply(&self, sudoku: &mut Sudoku) -> u32 {
+        sudoku.apply_constraints()
+    }
+}
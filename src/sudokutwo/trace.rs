@@ -0,0 +1,239 @@
+use std::convert::TryFrom;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sudokutwo::constraint::{cell_name, parse_cell};
+use crate::sudokutwo::entry_num::EntryNumThings;
+use crate::sudokutwo::solver_config::SolverConfig;
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+/// A number that got filled in, written `5=r1c3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Placement {
+    pub cell: usize,
+    pub num: u16,
+}
+
+impl Display for Placement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}={}", self.num, cell_name(self.cell))
+    }
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not a placement", s);
+        let (num, cell) = s.split_once('=').ok_or_else(invalid)?;
+        let num = num.trim().parse::<u16>().ok().filter(|n| (1..=9).contains(n)).ok_or_else(invalid)?;
+        let cell = parse_cell(cell.trim()).ok_or_else(invalid)?;
+        Ok(Placement { cell, num })
+    }
+}
+
+impl TryFrom<String> for Placement {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Placement> for String {
+    fn from(placement: Placement) -> Self {
+        placement.to_string()
+    }
+}
+
+/// How hard a technique is, and a puzzle going by the hardest technique it needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    /// Like singles and the variant rules
+    Easy,
+    /// Like naked subsets
+    Medium,
+    /// Like omissions
+    Hard,
+    /// The techniques get stuck, so the solver has to guess
+    Expert,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rating {
+    pub difficulty: Difficulty,
+    /// The hardest technique that made progress, None if none had to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardest_technique: Option<String>,
+    /// How many times a technique made progress
+    pub steps: usize,
+}
+
+/// A technique making progress
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraceStep {
+    pub technique: String,
+    pub difficulty: Difficulty,
+    /// How many possibilities it crossed out
+    pub eliminated: u32,
+    /// The numbers that got filled in by it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub placed: Vec<Placement>,
+}
+
+/// Everything the techniques did to a puzzle, up to where they solved it or got stuck
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SolveTrace {
+    pub steps: Vec<TraceStep>,
+    /// Whether the techniques solved the puzzle without guessing
+    pub solved: bool,
+    /// The grid where the techniques stopped, as a line
+    pub grid: String,
+    pub rating: Rating,
+}
+
+impl SolveTrace {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a trace always converts to JSON")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid solve trace: {}", e))
+    }
+}
+
+impl Sudoku {
+    /// Apply the techniques of the config like `SolverConfig::run` does, writing down every technique that made progress.
+    /// Never guesses: a puzzle the techniques can't solve is rated expert.
+    pub fn trace(&self, config: &SolverConfig) -> SolveTrace {
+        let mut s = self.clone();
+        let mut steps = vec!();
+        let mut progress = true;
+        while progress && s.is_valid().is_ok() {
+            progress = false;
+            for technique in config.techniques() {
+                let before = s.data;
+                let eliminated = technique.apply(&mut s);
+                if eliminated == 0 {
+                    continue;
+                }
+                let placed = (0..81)
+                    .filter(|i| !before[*i].is_fixed() && s.data[*i].is_fixed())
+                    .map(|cell| Placement { cell, num: s.data[cell].get_fixed() })
+                    .collect();
+                steps.push(TraceStep {
+                    technique: technique.name().to_string(),
+                    difficulty: technique.difficulty(),
+                    eliminated,
+                    placed,
+                });
+                progress = true;
+                if config.restarts_on_progress() {
+                    break;
+                }
+            }
+        }
+        let solved = s.is_solved();
+        // the first of the hardest steps
+        let hardest = steps.iter().rev().max_by_key(|step| step.difficulty);
+        let difficulty = match hardest {
+            _ if !solved => Difficulty::Expert,
+            Some(step) => step.difficulty,
+            None => Difficulty::Easy,
+        };
+        let rating = Rating {
+            difficulty,
+            hardest_technique: hardest.map(|step| step.technique.clone()),
+            steps: steps.len(),
+        };
+        SolveTrace { solved, grid: s.to_line(), rating, steps }
+    }
+
+    /// Rate the puzzle by tracing it with every technique, the simplest first
+    pub fn rate(&self) -> Rating {
+        self.trace(&SolverConfig::expert()).rating
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::solver_config::SolverConfig;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+    use crate::sudokutwo::technique::{NakedSingles, Technique};
+    use crate::sudokutwo::trace::{Difficulty, Placement, SolveTrace};
+
+    /// Naked singles under another name, claiming to be hard
+    struct Renamed;
+
+    impl Technique for Renamed {
+        fn name(&self) -> &'static str {
+            "something new"
+        }
+
+        fn difficulty(&self) -> Difficulty {
+            Difficulty::Hard
+        }
+
+        fn apply(&self, sudoku: &mut Sudoku) -> u32 {
+            NakedSingles.apply(sudoku)
+        }
+    }
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+    const HARD: &str = "400000805030000000000700000020000060000080400000010000000603070500200000104000000";
+
+    #[test]
+    fn trace_easy() {
+        let trace = Sudoku::new(String::from(EASY)).unwrap().trace(&SolverConfig::expert());
+        assert!(trace.solved);
+        assert_eq!(trace.rating.difficulty, Difficulty::Easy);
+        assert_eq!(trace.steps.iter().map(|step| step.placed.len()).sum::<usize>(), EASY.matches('0').count());
+        assert!(trace.steps.iter().all(|step| step.eliminated > 0));
+        assert_eq!(trace.grid, "483921657967345821251876493548132976729564138136798245372689514814253769695417382");
+    }
+
+    #[test]
+    fn rate_hard() {
+        let hard = Sudoku::new(String::from(HARD)).unwrap();
+        assert!(hard.rate().difficulty > Difficulty::Easy);
+        // with singles alone, the techniques get stuck
+        let trace = hard.trace(&SolverConfig::singles_only());
+        assert!(!trace.solved);
+        assert_eq!(trace.rating.difficulty, Difficulty::Expert);
+        assert!(trace.grid.contains('0'));
+    }
+
+    #[test]
+    fn rate_by_the_technique() {
+        let mut config = SolverConfig::singles_only();
+        config.push(Box::new(Renamed));
+        let trace = Sudoku::new(String::from(EASY)).unwrap().trace(&config);
+        assert!(trace.solved);
+        assert_eq!(trace.rating.difficulty, Difficulty::Easy);
+
+        let config = SolverConfig::new(vec!(Box::new(Renamed)), true);
+        let trace = Sudoku::new(String::from(EASY)).unwrap().trace(&config);
+        assert!(trace.steps.iter().all(|step| step.difficulty == Difficulty::Hard));
+        assert_eq!(trace.rating.hardest_technique.as_deref(), Some("something new"));
+    }
+
+    #[test]
+    fn trace_json() {
+        let trace = Sudoku::new(String::from(EASY)).unwrap().trace(&SolverConfig::expert());
+        let json = trace.to_json();
+        assert!(json.contains(r#""rating":{"difficulty":"easy","hardest_technique":"naked singles""#), "{}", json);
+        assert!(json.contains(r#"{"technique":"naked singles","difficulty":"easy","eliminated":"#), "{}", json);
+        assert_eq!(SolveTrace::from_json(&json), Ok(trace));
+        assert_eq!("4=r1c1".parse(), Ok(Placement { cell: 0, num: 4 }));
+        assert!(SolveTrace::from_json(r#"{"steps": []}"#).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::entry_num::{self, EntryNum};
+
+/// A change to a sudoku that keeps it valid: cells move around and numbers get new labels,
+/// but every solution of the old puzzle turns into a solution of the new one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transformation {
+    /// A quarter turn clockwise
+    Rotate90,
+    Rotate180,
+    /// A quarter turn anticlockwise
+    Rotate270,
+    /// Left becomes right
+    MirrorHorizontal,
+    /// Top becomes bottom
+    MirrorVertical,
+    /// Rows become columns
+    Transpose,
+    /// Number n becomes the (n - 1)th number of the list
+    Relabel([u16; 9]),
+    /// Two rows of the same band
+    SwapRows(usize, usize),
+    SwapBands(usize, usize),
+    /// Two columns of the same stack
+    SwapCols(usize, usize),
+    SwapStacks(usize, usize),
+}
+
+/// Swap a and b, leave anything else be
+fn swap(x: usize, a: usize, b: usize) -> usize {
+    if x == a {
+        b
+    } else if x == b {
+        a
+    } else {
+        x
+    }
+}
+
+/// The cells of a unit in a fixed order, to compare units as sets
+fn sorted(cells: impl Iterator<Item=usize>) -> Vec<usize> {
+    let mut res = cells.collect::<Vec<_>>();
+    res.sort_unstable();
+    res
+}
+
+impl Transformation {
+    /// Check that the transformation keeps a standard sudoku valid
+    pub fn check(&self) -> Result<(), String> {
+        match *self {
+            Transformation::Relabel(numbers) => {
+                let mut seen = 0;
+                for n in numbers.iter() {
+                    if !(1..=9).contains(n) {
+                        return Err(format!("{} is not a number of the sudoku", n));
+                    }
+                    seen |= entry_num::to_entry_num(*n);
+                }
+                if seen != entry_num::ALL {
+                    return Err(format!("Relabelling {:?} uses a number twice", numbers));
+                }
+            }
+            Transformation::SwapRows(a, b) | Transformation::SwapCols(a, b)
+            if a >= 9 || b >= 9 || a / 3 != b / 3 => {
+                return Err(format!("Can only swap lines of the same band or stack, not {} and {}", a, b));
+            }
+            Transformation::SwapBands(a, b) | Transformation::SwapStacks(a, b) if a >= 3 || b >= 3 => {
+                return Err(format!("There are only 3 bands and stacks, can't swap {} and {}", a, b));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Where the cell ends up
+    pub fn map_cell(&self, cell: usize) -> usize {
+        let (row, col) = (cell / 9, cell % 9);
+        let (row, col) = match *self {
+            Transformation::Rotate90 => (col, 8 - row),
+            Transformation::Rotate180 => (8 - row, 8 - col),
+            Transformation::Rotate270 => (8 - col, row),
+            Transformation::MirrorHorizontal => (row, 8 - col),
+            Transformation::MirrorVertical => (8 - row, col),
+            Transformation::Transpose => (col, row),
+            Transformation::Relabel(_) => (row, col),
+            Transformation::SwapRows(a, b) => (swap(row, a, b), col),
+            Transformation::SwapBands(a, b) => (swap(row / 3, a, b) * 3 + row % 3, col),
+            Transformation::SwapCols(a, b) => (row, swap(col, a, b)),
+            Transformation::SwapStacks(a, b) => (row, swap(col / 3, a, b) * 3 + col % 3),
+        };
+        row * 9 + col
+    }
+
+    /// The new label of the number
+    pub fn map_number(&self, n: u16) -> u16 {
+        match self {
+            Transformation::Relabel(numbers) => numbers[n as usize - 1],
+            _ => n,
+        }
+    }
+
+    /// Relabel every possibility of a cell
+    pub fn map_entry(&self, en: EntryNum) -> EntryNum {
+        (1..=9)
+            .filter(|n| en & entry_num::to_entry_num(*n) > 0)
+            .fold(0, |cur, n| cur | entry_num::to_entry_num(self.map_number(n)))
+    }
+
+    /// The transformation that undoes this one
+    pub fn inverse(&self) -> Transformation {
+        match *self {
+            Transformation::Rotate90 => Transformation::Rotate270,
+            Transformation::Rotate270 => Transformation::Rotate90,
+            Transformation::Relabel(numbers) => {
+                let mut inverse = [0; 9];
+                for (i, n) in numbers.iter().enumerate() {
+                    inverse[*n as usize - 1] = i as u16 + 1;
+                }
+                Transformation::Relabel(inverse)
+            }
+            other => other,
+        }
+    }
+}
+
+impl Sudoku {
+    /// Apply the transformation, keeping the possibilities of every cell
+    /// Returns: the new puzzle, or an error if the transformation breaks a unit of the layout
+    /// or the sudoku has variant rules, as those can't be moved along
+    pub fn transform(&self, transformation: &Transformation) -> Result<Sudoku, String> {
+        transformation.check()?;
+        if !self.constraints.is_empty() {
+            return Err(String::from("Sudokus with variant rules can't be transformed"));
+        }
+        let units = self.layout.units().iter()
+            .map(|(_, cells)| sorted(cells.iter().copied()))
+            .collect::<HashSet<_>>();
+        if let Some((unit, _)) = self.layout.units().iter()
+            .find(|(_, cells)| !units.contains(&sorted(cells.iter().map(|i| transformation.map_cell(*i))))) {
+            return Err(format!("{:?} doesn't keep {} together", transformation, unit));
+        }
+        let mut res = self.clone();
+        for (i, en) in self.data.iter().enumerate() {
+            let cell = transformation.map_cell(i);
+            res.data[cell] = transformation.map_entry(*en);
+            res.origins[cell] = self.origins[i];
+        }
+        Ok(res)
+    }
+
+    /// Apply the transformations one after the other
+    pub fn transform_all(&self, transformations: &[Transformation]) -> Result<Sudoku, String> {
+        transformations.iter().try_fold(self.clone(), |sudoku, t| sudoku.transform(t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sudokutwo::layout::Layout;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+    use crate::sudokutwo::transform::Transformation;
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+
+    const TRANSFORMATIONS: [Transformation; 11] = [
+        Transformation::Rotate90, Transformation::Rotate180, Transformation::Rotate270,
+        Transformation::MirrorHorizontal, Transformation::MirrorVertical, Transformation::Transpose,
+        Transformation::Relabel([2, 3, 4, 5, 6, 7, 8, 9, 1]),
+        Transformation::SwapRows(3, 5), Transformation::SwapBands(0, 2),
+        Transformation::SwapCols(7, 8), Transformation::SwapStacks(1, 2),
+    ];
+
+    #[test]
+    fn map_cells() {
+        assert_eq!(Transformation::Rotate90.map_cell(0), 8);
+        assert_eq!(Transformation::Rotate270.map_cell(0), 72);
+        assert_eq!(Transformation::Transpose.map_cell(1), 9);
+        assert_eq!(Transformation::SwapBands(0, 2).map_cell(10), 64);
+        assert_eq!(Transformation::SwapStacks(0, 1).map_cell(10), 13);
+        assert_eq!(Transformation::Relabel([2, 3, 4, 5, 6, 7, 8, 9, 1]).map_number(9), 1);
+    }
+
+    #[test]
+    fn inverse_undoes() {
+        let easy = Sudoku::new(String::from(EASY)).unwrap();
+        for t in TRANSFORMATIONS.iter() {
+            let moved = easy.transform(t).unwrap();
+            assert_ne!(moved.to_line(), EASY, "{:?}", t);
+            assert_eq!(moved.transform(&t.inverse()).unwrap().to_line(), EASY, "{:?}", t);
+            assert_eq!(moved.is_equivalent(&easy), Ok(true));
+        }
+        let turned = easy.transform_all(&[Transformation::Rotate90; 4]).unwrap();
+        assert_eq!(turned.to_line(), EASY);
+    }
+
+    #[test]
+    fn givens_move_along() {
+        let easy = Sudoku::new(String::from(EASY)).unwrap();
+        for t in TRANSFORMATIONS.iter() {
+            let moved = easy.transform(t).unwrap();
+            assert_eq!(moved.givens_line(), moved.to_line(), "{:?}", t);
+        }
+        let mut solved = easy.clone();
+        assert!(solved.attempt_solve());
+        let moved = solved.transform(&Transformation::Rotate90).unwrap();
+        assert_eq!(moved.givens_line(), easy.transform(&Transformation::Rotate90).unwrap().to_line());
+    }
+
+    #[test]
+    fn solutions_move_along() {
+        let mut solved = Sudoku::new(String::from(EASY)).unwrap();
+        assert!(solved.attempt_solve());
+        let easy = Sudoku::new(String::from(EASY)).unwrap();
+        for t in TRANSFORMATIONS.iter() {
+            let mut moved = easy.transform(t).unwrap();
+            assert!(moved.attempt_solve());
+            assert_eq!(moved.to_line(), solved.transform(t).unwrap().to_line());
+        }
+    }
+
+    #[test]
+    fn invalid_transformations() {
+        let easy = Sudoku::new(String::from(EASY)).unwrap();
+        assert!(easy.transform(&Transformation::SwapRows(2, 3)).is_err());
+        assert!(easy.transform(&Transformation::SwapStacks(0, 3)).is_err());
+        assert!(easy.transform(&Transformation::Relabel([1, 1, 3, 4, 5, 6, 7, 8, 9])).is_err());
+
+        // turning keeps the diagonals, swapping bands doesn't
+        let x = Sudoku::with_layout("0".repeat(81), Layout::diagonal()).unwrap();
+        assert!(x.transform(&Transformation::Rotate90).is_ok());
+        assert!(x.transform(&Transformation::SwapBands(0, 1)).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt::{Display, Error, Formatter};
+
+use crate::sudokutwo::constraint::cell_name;
+use crate::sudokutwo::entry_num::{self, EntryNumThings};
+use crate::sudokutwo::layout::Unit;
+use crate::sudokutwo::Sudoku;
+
+/// A single reason why a puzzle can't be solved anymore
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Conflict {
+    /// The number is filled in at two cells of the unit
+    Duplicate { unit: Unit, num: u16, cells: (usize, usize) },
+    /// Every number has been crossed out in the cell
+    EmptyCell { cell: usize },
+    /// The number has no place left in the unit
+    NoPlace { unit: Unit, num: u16 },
+    /// A variant rule can't be met anymore, as described by the rule
+    Rule(String),
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            Conflict::Duplicate { unit, num, cells: (a, b) } =>
+                write!(f, "{} appears at both {} and {} in {}", num, cell_name(*a), cell_name(*b), unit),
+            Conflict::EmptyCell { cell } => write!(f, "At {}, there are no possibilities left", cell_name(*cell)),
+            Conflict::NoPlace { unit, num } => write!(f, "{} is not possible in {}", num, unit),
+            Conflict::Rule(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Sudoku {
+    /// Find every conflict in the puzzle, instead of stopping at the first like `is_valid`
+    /// Returns: the empty cells, then the conflicts of each unit in layout order, then the broken variant rules
+    pub fn validate(&self) -> Vec<Conflict> {
+        let mut res = (0..81)
+            .filter(|i| self.data[*i] == 0)
+            .map(|cell| Conflict::EmptyCell { cell })
+            .collect::<Vec<_>>();
+        for (unit, cells) in self.layout.units() {
+            let mut first_at: [Option<usize>; 10] = [None; 10];
+            let mut possible = 0;
+            for cell in cells.iter() {
+                let en = self.data[*cell];
+                possible |= en;
+                if !en.is_fixed() {
+                    continue;
+                }
+                let num = en.get_fixed();
+                match first_at[num as usize] {
+                    Some(first) => res.push(Conflict::Duplicate { unit: *unit, num, cells: (first, *cell) }),
+                    None => first_at[num as usize] = Some(*cell),
+                }
+            }
+            res.extend((1..=9)
+                .filter(|num| possible & entry_num::to_entry_num(*num) == 0)
+                .map(|num| Conflict::NoPlace { unit: *unit, num }));
+        }
+        res.extend(self.constraints.iter()
+            .filter_map(|constraint| constraint.check(&self.data).err())
+            .map(Conflict::Rule));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::sudokutwo::anti_chess::AntiMove;
+    use crate::sudokutwo::entry_num::{FIVE, NINE};
+    use crate::sudokutwo::layout::Unit;
+    use crate::sudokutwo::Sudoku;
+    use crate::sudokutwo::sudoku_api::SudokuApi;
+    use crate::sudokutwo::validation::Conflict;
+
+    #[test]
+    fn every_duplicate() {
+        let s = Sudoku::new(format!("55{}5{}", "0".repeat(7), "0".repeat(71))).unwrap();
+        let conflicts = s.validate();
+        assert_eq!(conflicts, vec!(
+            Conflict::Duplicate { unit: Unit::Row(0), num: 5, cells: (0, 1) },
+            Conflict::Duplicate { unit: Unit::Col(0), num: 5, cells: (0, 9) },
+            Conflict::Duplicate { unit: Unit::Box(0), num: 5, cells: (0, 1) },
+            Conflict::Duplicate { unit: Unit::Box(0), num: 5, cells: (0, 9) },
+        ));
+        let messages = conflicts.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        assert_eq!(messages, vec!(
+            "5 appears at both r1c1 and r1c2 in r1",
+            "5 appears at both r1c1 and r2c1 in c1",
+            "5 appears at both r1c1 and r1c2 in b1",
+            "5 appears at both r1c1 and r2c1 in b1",
+        ));
+    }
+
+    #[test]
+    fn empty_cells_and_missing_numbers() {
+        let mut s = Sudoku::new("0".repeat(81)).unwrap();
+        s.data[10] = 0;
+        for cell in 72..81 {
+            s.data[cell] &= !NINE;
+        }
+        assert_eq!(s.validate(), vec!(
+            Conflict::EmptyCell { cell: 10 },
+            Conflict::NoPlace { unit: Unit::Row(8), num: 9 },
+        ));
+        assert_eq!(s.validate()[1].to_string(), "9 is not possible in r9");
+        assert_eq!(s.is_valid(), Err(String::from("At r2c2, there are no possibilities left")));
+        assert_eq!(Sudoku::new("0".repeat(81)).unwrap().validate(), vec!());
+    }
+
+    #[test]
+    fn broken_rules() {
+        let mut s = Sudoku::new("0".repeat(81)).unwrap();
+        s.data[0] = FIVE;
+        s.data[10] = FIVE;
+        s.add_constraint(Arc::new(AntiMove::king()));
+        s.add_constraint(Arc::new(AntiMove::knight()));
+        let conflicts = s.validate();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[1], Conflict::Rule(String::from("r1c1 and r2c2 are a king's move apart and both hold 5")));
+    }
+}
@@ -0,0 +1,184 @@
+use std::fmt::{Display, Error, Formatter};
+use std::io::{self, BufRead};
+
+use crate::sudokutwo::constraint::cell_name;
+use crate::sudokutwo::Sudoku;
+use crate::sudokutwo::sudoku_api::SudokuApi;
+
+/// The outcome of checking a claimed solution of one puzzle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verification {
+    /// The line number, starting at 1
+    pub line: usize,
+    /// Everything that's wrong with the solution; it passes when there is nothing
+    pub problems: Vec<String>,
+}
+
+impl Verification {
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Display for Verification {
+    /// Formats as `line,pass` or `line,fail,problem; problem; ...`
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        if self.passed() {
+            write!(f, "{},pass", self.line)
+        } else {
+            write!(f, "{},fail,{}", self.line, self.problems.join("; "))
+        }
+    }
+}
+
+/// "cell" or "cells", to go with the count
+fn cells(count: usize) -> &'static str {
+    if count == 1 { "cell" } else { "cells" }
+}
+
+/// Check a claimed solution: it has to be complete and valid, keep every given of the puzzle,
+/// and be what the solver finds. A puzzle with more than one solution fails on its own,
+/// as there is no single solution to compare with.
+/// Returns: the problems found, none if the solution is right
+pub fn verify_pair(puzzle: &str, solution: &str) -> Vec<String> {
+    let (puzzle, solution) = (puzzle.trim(), solution.trim());
+    let mut problems = vec!();
+    let claimed = match Sudoku::new(solution.to_string()) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            problems.push(format!("the solution can't be read: {}", e));
+            None
+        }
+    };
+    if let Some(claimed) = claimed.as_ref() {
+        let empty = claimed.count_unfixed();
+        if empty > 0 {
+            problems.push(format!("the solution has {} empty {}", empty, cells(empty)));
+        }
+        if let Err(e) = claimed.is_valid() {
+            problems.push(format!("the solution is not valid: {}", e));
+        }
+    }
+    let mut solved = match Sudoku::new(puzzle.to_string()) {
+        Ok(s) => s,
+        Err(e) => {
+            problems.push(format!("the puzzle can't be read: {}", e));
+            return problems;
+        }
+    };
+    if claimed.is_some() {
+        problems.extend(puzzle.chars().zip(solution.chars())
+            .enumerate()
+            .filter(|(_, (p, s))| *p != '0' && p != s)
+            .map(|(cell, _)| format!("the solution changes the given at {}", cell_name(cell))));
+    }
+    match solved.count_solutions(2) {
+        0 => problems.push(String::from("the solver finds no solution for the puzzle")),
+        1 if claimed.is_some() => {
+            solved.attempt_solve();
+            let line = solved.to_line();
+            let different = line.chars().zip(solution.chars()).filter(|(a, b)| a != b).count();
+            if different > 0 {
+                problems.push(format!("the solver's solution differs in {} {}", different, cells(different)));
+            }
+        }
+        1 => {}
+        _ => problems.push(String::from("the puzzle has more than one solution")),
+    }
+    problems
+}
+
+/// Check the solutions of a file of puzzles, line by line against a file of solutions
+pub fn verify_files<P: BufRead, S: BufRead>(puzzles: P, solutions: S) -> io::Result<Vec<Verification>> {
+    let puzzles = puzzles.lines().collect::<io::Result<Vec<_>>>()?;
+    let solutions = solutions.lines().collect::<io::Result<Vec<_>>>()?;
+    let res = (0..puzzles.len().max(solutions.len()))
+        .map(|i| {
+            let problems = match (puzzles.get(i), solutions.get(i)) {
+                (Some(puzzle), Some(solution)) => verify_pair(puzzle, solution),
+                (Some(_), None) => vec!(String::from("there is no solution for the puzzle")),
+                _ => vec!(String::from("there is no puzzle for the solution")),
+            };
+            Verification { line: i + 1, problems }
+        })
+        .collect();
+    Ok(res)
+}
+
+/// Check the solutions in `puzzle,solution` lines, skipping blank lines like `batch::solve_stream` does
+pub fn verify_csv<R: BufRead>(input: R) -> io::Result<Vec<Verification>> {
+    input.lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |l| !l.trim().is_empty()))
+        .map(|(i, line)| {
+            let line = line?;
+            let problems = match line.split_once(',') {
+                Some((puzzle, solution)) => verify_pair(puzzle, solution),
+                None => vec!(String::from("expected a puzzle and a solution separated by a comma")),
+            };
+            Ok(Verification { line: i + 1, problems })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use crate::sudokutwo::verify::{verify_csv, verify_files, verify_pair};
+
+    const EASY: &str = "003020600900305001001806400008102900700000008006708200002609500800203009005010300";
+    const SOLUTION: &str = "483921657967345821251876493548132976729564138136798245372689514814253769695417382";
+
+    #[test]
+    fn reference_solution_passes() {
+        let puzzles = BufReader::new(File::open("resources/sudoku-easy-1.txt").unwrap());
+        let solutions = BufReader::new(File::open("resources/sudoku-easy-1-solution.txt").unwrap());
+        let report = verify_files(puzzles, solutions).unwrap();
+        assert_eq!(report.len(), 1);
+        assert!(report[0].passed(), "{}", report[0]);
+        assert_eq!(report[0].to_string(), "1,pass");
+    }
+
+    #[test]
+    fn wrong_solutions() {
+        // swapping two numbers in a row keeps the rows complete, but breaks the columns and a given
+        let swapped = format!("{}{}{}{}", &SOLUTION[..2], &SOLUTION[3..4], &SOLUTION[2..3], &SOLUTION[4..]);
+        let problems = verify_pair(EASY, &swapped);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(problems[1].ends_with("changes the given at r1c3"));
+
+        let incomplete = format!("0{}", &SOLUTION[1..]);
+        assert_eq!(verify_pair(EASY, &incomplete),
+                   vec!(String::from("the solution has 1 empty cell"),
+                        String::from("the solver's solution differs in 1 cell")));
+        assert_eq!(verify_pair("123", SOLUTION).len(), 1);
+
+        // every changed given is reported, not just the first
+        let shifted = format!("{}{}", &SOLUTION[1..], &SOLUTION[..1]);
+        let problems = verify_pair(EASY, &shifted);
+        let changed = problems.iter().filter(|p| p.starts_with("the solution changes the given at")).count();
+        assert_eq!(changed, EASY.chars().filter(|c| *c != '0').count(), "{:?}", problems);
+    }
+
+    #[test]
+    fn puzzle_without_unique_solution() {
+        // any complete grid solves the empty grid, but it has no single solution to compare with
+        let problems = verify_pair(&"0".repeat(81), SOLUTION);
+        assert_eq!(problems, vec!(String::from("the puzzle has more than one solution")));
+    }
+
+    #[test]
+    fn csv_report() {
+        // blank lines are skipped, but still counted
+        let csv = format!("{},{}\n{}\n\n{},{}\n", EASY, SOLUTION, EASY, EASY, EASY);
+        let report = verify_csv(csv.as_bytes()).unwrap();
+        assert_eq!(report.iter().map(|v| v.passed()).collect::<Vec<_>>(), vec!(true, false, false));
+        assert!(report[1].to_string().starts_with("2,fail,expected"));
+        assert_eq!(report[2].line, 4);
+
+        let report = verify_files(EASY.as_bytes(), format!("{}\n{}", SOLUTION, SOLUTION).as_bytes()).unwrap();
+        assert!(report[0].passed() && !report[1].passed());
+    }
+}